@@ -0,0 +1,41 @@
+use rogue_lib::{config::Config, remote::Server};
+use std::{net::TcpListener, time::Duration};
+
+/// Server che ospita una partita per dei giocatori remoti.\
+/// Utilizzo: server [indirizzo] [numero giocatori] [seed]\
+/// Di base il server ascolta su 127.0.0.1:7878 ed aspetta un solo giocatore.
+fn main() -> std::io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let address = args.next().unwrap_or("127.0.0.1:7878".to_string());
+    let players = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(1);
+    let seed = args.next().and_then(|arg| arg.parse().ok());
+
+    let config = Config {
+        game_seed: seed.unwrap_or_else(rand::random),
+        ..Default::default()
+    };
+    let mut server = Server::new(config);
+    server.players = players;
+    server.timeout = Some(Duration::from_secs(60));
+
+    let listener = TcpListener::bind(&address)?;
+    println!("Waiting for {players} players on {address}");
+    let dungeon = server.run(&listener)?;
+
+    println!("Game over after {} turns", dungeon.get_turn());
+    for player in dungeon.get_players().chain(dungeon.get_departed()) {
+        let statistics = player.get_statistics();
+        let outcome = match dungeon.get_outcome(player.get_name()) {
+            Some(outcome) => format!("{outcome:?}"),
+            None => "still playing".to_string(),
+        };
+        println!(
+            "{}: floor {}, {} kills, {}",
+            player.get_name(),
+            statistics.deepest_floor,
+            statistics.kills,
+            outcome
+        );
+    }
+    Ok(())
+}
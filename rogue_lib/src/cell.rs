@@ -1,6 +1,8 @@
 use super::{
     entities::{Action, Direction, Entity},
     floor::Floor,
+    item::Item,
+    status::{Stacking, Status},
 };
 use dyn_clone::{clone_trait_object, DynClone};
 use rand::Rng;
@@ -10,12 +12,14 @@ use std::fmt::Display;
 /// Rappresentazione di una cella di spazio.\
 /// Essa ha diversi valori in base a cosa si può fare o meno su di essa.
 /// Nel caso in cui passi sopra una entià esiste un metodo entity_over che
-/// gestisce le varie casistiche.
+/// gestisce le varie casistiche.\
+/// Gli oggetti contenuti in Item non vengono raccolti automaticamente, ma solo tramite Action::PickUp.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum Cell {
     Entrance,
     Exit,
     Special(Box<dyn Effect>),
+    Item(Box<dyn Item>),
     Wall,
     Empty,
 }
@@ -49,6 +53,7 @@ impl Cell {
             Cell::Entrance => ' ',
             Cell::Exit => '¤',
             Cell::Special(effect) => effect.as_char(),
+            Cell::Item(item) => item.as_char(),
             Cell::Wall => '█',
             Cell::Empty => ' ',
         }
@@ -85,13 +90,40 @@ pub trait Effect: DynClone + core::fmt::Debug {
     /// Tramite l'entità si può anche accedere al piano dove si trova per
     /// poter modificare eventualmente qualcosa.
     fn apply_to(&self, entity: &mut Entity, floor: &mut Floor);
+    /// Restituisce il nome dell'effetto in modo da poterlo mostrare al giocatore.\
+    /// Di base è il nome con cui l'effetto viene serializzato.
+    fn get_name(&self) -> String {
+        self.typetag_name().to_string()
+    }
+    /// Restituisce una breve descrizione di cosa fa l'effetto a chi ci passa sopra.\
+    /// Di base è vuota.
+    fn get_description(&self) -> String {
+        String::new()
+    }
     /// Ritorna un carattere che rappresenta l'effetto.
     fn as_char(&self) -> char {
         '?'
     }
+    /// Ritorna il colore con cui mostrare l'effetto.\
+    /// Insieme al carattere permette di distinguere gli effetti tra di loro.
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Magenta
+    }
 }
 clone_trait_object!(Effect);
 
+/// Colore con cui viene mostrato un effetto.\
+/// È indipendente dall'interfaccia utilizzata, che poi lo trasforma nel colore che può mostrare.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum EffectColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
 /// Permette di dare un danno istantaneo a qualunque entità ci passi sopra.\
 /// Una volta utilizzato verrà rimosso dal piano.\
 /// Nel caso in cui il danno sia negativo, l'entità verrà curata
@@ -106,6 +138,20 @@ impl Effect for InstantDamage {
     fn apply_to(&self, entity: &mut Entity, _floor: &mut Floor) {
         entity.apply_damage(self.0);
     }
+    fn get_name(&self) -> String {
+        if self.0 <= 0 {
+            "Instant healing".to_string()
+        } else {
+            "Instant damage".to_string()
+        }
+    }
+    fn get_description(&self) -> String {
+        if self.0 <= 0 {
+            format!("Heals {} health once", -self.0)
+        } else {
+            format!("Deals {} damage once", self.0)
+        }
+    }
     fn as_char(&self) -> char {
         if self.0 <= 0 {
             '+'
@@ -113,12 +159,21 @@ impl Effect for InstantDamage {
             '-'
         }
     }
+    fn get_color(&self) -> EffectColor {
+        if self.0 <= 0 {
+            EffectColor::Green
+        } else {
+            EffectColor::Red
+        }
+    }
 }
 
 /// Permettere di infliggere lo stato di confuzione ad una entità.\
-/// Esso ignora il successivo comando che verrà impartito all'entità
-/// con una probabilità del 50% e inserirà un movimento in una direzione casuale.\
-/// Come parametro si può passare per quanti turni l'effetto dura
+/// Finchè lo stato è attivo, alla fine di ogni turno c'è una probabilità del 50% che il
+/// successivo comando impartito all'entità venga ignorato e sostituito da un movimento
+/// in una direzione casuale.\
+/// Come parametro si può passare per quanti turni lo stato dura.\
+/// L'effetto rimane sul terreno, e una nuova confusione rinnova quella attiva.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Confusion(pub u8);
 #[typetag::serde]
@@ -127,21 +182,57 @@ impl Effect for Confusion {
         true
     }
     fn apply_to(&self, entity: &mut Entity, floor: &mut Floor) {
-        if self.0 > 0 {
-            let rng = floor.get_rng();
-            if rng.gen_bool(0.5) {
-                let random_direction = Direction::random(rng);
-                entity.buffer = Action::Move(random_direction);
-            }
-            entity.add_effect(Box::new(Self(self.0 - 1)));
+        entity.add_status(Box::new(self.clone()), floor);
+    }
+    fn get_name(&self) -> String {
+        Status::get_name(self)
+    }
+    fn get_description(&self) -> String {
+        Status::get_description(self)
+    }
+    fn as_char(&self) -> char {
+        Status::as_char(self)
+    }
+    fn get_color(&self) -> EffectColor {
+        Status::get_color(self)
+    }
+}
+#[typetag::serde]
+impl Status for Confusion {
+    fn get_name(&self) -> String {
+        "Confusion".to_string()
+    }
+    fn get_description(&self) -> String {
+        format!(
+            "Moves in a random direction half of the time for {} turns",
+            self.0
+        )
+    }
+    fn get_duration(&self) -> u32 {
+        self.0 as u32
+    }
+    fn on_tick(&self, entity: &mut Entity, floor: &mut Floor) {
+        let rng = floor.get_rng();
+        if rng.gen_bool(0.5) {
+            entity.buffer = Action::Move(Direction::random(rng));
         }
     }
+    fn as_char(&self) -> char {
+        '~'
+    }
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Magenta
+    }
 }
 
 /// Permette di infliggere un danno nel tempo.\
 /// Similmente a InstantDamage, se il danno è negativo allora il personaggio verrà curato,
 /// sempre a patto che la sua vita sia un valore positivo.\
-/// L'effetto dura un determinato numero di turni.
+/// L'effetto dura un determinato numero di turni: una volta applicato infligge subito il
+/// danno del primo turno e aggiunge all'entità se stesso come stato per i turni rimanenti,
+/// in modo che il danno venga sempre registrato come TurnBasedDamage.\
+/// Più effetti si sommano, ognuno con la propria durata; per i nuovi contenuti è preferibile
+/// usare direttamente gli stati Poison e Regeneration.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TurnBasedDamage {
     time: u8,
@@ -157,15 +248,74 @@ impl Effect for TurnBasedDamage {
     fn is_persistent(&self) -> bool {
         false
     }
-    fn apply_to(&self, entity: &mut Entity, _floor: &mut Floor) {
+    fn apply_to(&self, entity: &mut Entity, floor: &mut Floor) {
         if self.time > 0 {
             entity.apply_damage(self.damage);
-            if self.time > 1 {
-                entity.add_effect(Box::new(Self {
+            entity.add_status(
+                Box::new(Self {
                     time: self.time - 1,
                     damage: self.damage,
-                }));
-            }
+                }),
+                floor,
+            );
+        }
+    }
+    fn get_name(&self) -> String {
+        Status::get_name(self)
+    }
+    fn get_description(&self) -> String {
+        Status::get_description(self)
+    }
+    fn as_char(&self) -> char {
+        Status::as_char(self)
+    }
+    fn get_color(&self) -> EffectColor {
+        Status::get_color(self)
+    }
+}
+#[typetag::serde]
+impl Status for TurnBasedDamage {
+    fn get_name(&self) -> String {
+        if self.damage <= 0 {
+            "Healing over time".to_string()
+        } else {
+            "Damage over time".to_string()
+        }
+    }
+    fn get_description(&self) -> String {
+        if self.damage <= 0 {
+            format!(
+                "Heals {} health per turn for {} turns",
+                -self.damage, self.time
+            )
+        } else {
+            format!(
+                "Deals {} damage per turn for {} turns",
+                self.damage, self.time
+            )
+        }
+    }
+    fn get_duration(&self) -> u32 {
+        self.time as u32
+    }
+    fn get_stacking(&self) -> Stacking {
+        Stacking::Stack
+    }
+    fn on_tick(&self, entity: &mut Entity, _floor: &mut Floor) {
+        entity.apply_damage(self.damage);
+    }
+    fn as_char(&self) -> char {
+        if self.damage <= 0 {
+            '&'
+        } else {
+            '%'
+        }
+    }
+    fn get_color(&self) -> EffectColor {
+        if self.damage <= 0 {
+            EffectColor::Cyan
+        } else {
+            EffectColor::Yellow
         }
     }
 }
@@ -1,9 +1,18 @@
 use super::{
     cell::{Confusion, Effect, InstantDamage},
-    entities::{Behavior, RandomMovement},
+    entities::{Behavior, Chaser, CombatStats, Growth, RandomMovement, Statistics},
+    generator::{FloorGenerator, RoomsAndMazes},
+    item::{Item, Potion, Weapon},
+    prefab::{Prefab, PrefabTile},
+    status::{Haste, Poison, Regeneration, Teleport},
 };
 use serde::{Deserialize, Serialize};
-use std::ops::Range;
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader},
+    ops::Range,
+};
 
 /// Struttura di configurazione per la creazione di un dungeon.\
 /// Ogni elemento indica un parametro per la generazione di un piano o di una entitità.\
@@ -14,17 +23,220 @@ pub struct Config {
     pub maze_generation: ConfigMaze,
     pub effects_total: usize,
     pub effects: Vec<ConfigEffect>,
+    pub items_total: usize,
+    pub items: Vec<ConfigItem>,
     pub entities_total: usize,
     pub entities: Vec<ConfigEntity>,
+    #[serde(default)]
+    pub bosses: Vec<ConfigBoss>,
+    #[serde(default)]
+    pub victory: ConfigVictory,
     pub player_stats: ConfigPlayer,
 }
 
+impl Config {
+    /// Carica la configurazione da un file.\
+    /// Il file deve essere formattato tramite json, altrimenti viene ritornato un errore.\
+    /// La configurazione caricata non viene controllata, per farlo bisogna usare validate.
+    pub fn load(filename: &str) -> io::Result<Self> {
+        let file = File::open(filename)?;
+        let reader = BufReader::new(file);
+        let config: Self = serde_json::from_reader(reader)?;
+        Ok(config)
+    }
+
+    /// Controlla che la configurazione possa essere usata per generare un dungeon.\
+    /// Nel caso in cui non sia così, vengono restituiti tutti i problemi trovati, in modo da
+    /// poterli mostrare all'utente invece di fallire durante la generazione dei piani.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = vec![];
+        let maze = &self.maze_generation;
+        if maze.floor_size.is_empty() {
+            errors.push(ConfigError::EmptyRange("floor_size"));
+        }
+        if maze.room_size.is_empty() {
+            errors.push(ConfigError::EmptyRange("room_size"));
+        }
+        if !maze.floor_size.is_empty() && maze.floor_size.start < MIN_FLOOR_SIZE {
+            errors.push(ConfigError::FloorBelowMinimum(maze.floor_size.start));
+        }
+        if !maze.floor_size.is_empty()
+            && !maze.room_size.is_empty()
+            && maze.floor_size.start < maze.room_size.end
+        {
+            errors.push(ConfigError::FloorTooSmall {
+                floor: maze.floor_size.start,
+                room: maze.room_size.end - 1,
+            });
+        }
+        if maze.straight_percentage > 100 {
+            errors.push(ConfigError::StraightPercentage(maze.straight_percentage));
+        }
+        if let Err(problem) = maze.generator.validate() {
+            errors.push(ConfigError::Generator(problem));
+        }
+        if self.effects_total > 0 && self.effects.is_empty() {
+            errors.push(ConfigError::EmptyList("effects"));
+        }
+        if self.items_total > 0 && self.items.is_empty() {
+            errors.push(ConfigError::EmptyList("items"));
+        }
+        if self.entities_total > 0 && self.entities.is_empty() {
+            errors.push(ConfigError::EmptyList("entities"));
+        }
+        let uncovered = [
+            (
+                self.effects_total,
+                "effects",
+                uncovered_floor(self.effects.iter().map(|e| &e.floors)),
+            ),
+            (
+                self.items_total,
+                "items",
+                uncovered_floor(self.items.iter().map(|i| &i.floors)),
+            ),
+            (
+                self.entities_total,
+                "entities",
+                uncovered_floor(self.entities.iter().map(|e| &e.floors)),
+            ),
+        ];
+        for (total, list, floor) in uncovered {
+            match floor {
+                Some(floor) if total > 0 => {
+                    errors.push(ConfigError::UncoveredFloor { list, floor })
+                }
+                _ => (),
+            }
+        }
+
+        for config in &maze.prefabs {
+            for tile in config.prefab.legend.values() {
+                match tile {
+                    PrefabTile::Spawn(name) if self.find_spawn(name).is_none() => {
+                        errors.push(ConfigError::UnknownSpawn {
+                            prefab: config.prefab.name.clone(),
+                            name: name.clone(),
+                        })
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        if self.player_stats.health <= 0 {
+            errors.push(ConfigError::NoHealth("player".to_string()));
+        }
+        let bosses = self
+            .bosses
+            .iter()
+            .flat_map(|boss| std::iter::once(&boss.boss).chain(&boss.minions));
+        for entity in self.entities.iter().chain(bosses) {
+            if entity.health <= 0 {
+                errors.push(ConfigError::NoHealth(entity.name.clone()));
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Cerca per nome l'entità da generare nel punto di una stanza prefabbricata.\
+    /// Vengono cercate prima le entità normali e poi i boss insieme ai loro seguaci; insieme
+    /// all'entità viene indicato se essa è un boss.
+    pub fn find_spawn(&self, name: &str) -> Option<(&ConfigEntity, bool)> {
+        let entities = self.entities.iter().map(|entity| (entity, false));
+        let bosses = self.bosses.iter().flat_map(|boss| {
+            let minions = boss.minions.iter().map(|minion| (minion, false));
+            std::iter::once((&boss.boss, true)).chain(minions)
+        });
+        entities
+            .chain(bosses)
+            .find(|(entity, _)| entity.name == name)
+    }
+}
+
+/// Cerca il primo piano non compreso in nessuno dei range indicati, fino all'ultimo piano
+/// in cui almeno uno di essi può apparire.\
+/// Oltre quel piano non appare più nessun elemento, quindi i piani più profondi vengono
+/// semplicemente lasciati senza di essi dal generatore.
+fn uncovered_floor<'a>(ranges: impl Iterator<Item = &'a Range<usize>> + Clone) -> Option<usize> {
+    let last = ranges.clone().map(|range| range.end).max()?;
+    (0..last).find(|floor| !ranges.clone().any(|range| range.contains(floor)))
+}
+
+/// Problema trovato in una configurazione tramite Config::validate.\
+/// *EmptyRange* indica che il range del campo indicato non contiene nessun valore.\
+/// *FloorTooSmall* indica che il piano più piccolo non è più grande della stanza più grande.\
+/// *StraightPercentage* indica che la percentuale dei corridoi dritti è maggiore di 100.\
+/// *Generator* indica che i parametri del generatore dei piani non sono validi, con il motivo.\
+/// *EmptyList* indica che la lista indicata è vuota, ma ne vengono richiesti degli elementi.\
+/// *UncoveredFloor* indica il primo piano in cui nessun elemento della lista indicata può apparire,
+/// nonostante ne vengano richiesti degli elementi.\
+/// *UnknownSpawn* indica che la stanza prefabbricata indicata genera un'entità con un nome
+/// che non corrisponde a nessuna entità, boss o seguace della configurazione.\
+/// *NoHealth* indica che l'entità con il nome indicato inizia senza vita.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    EmptyRange(&'static str),
+    FloorTooSmall { floor: usize, room: usize },
+    FloorBelowMinimum(usize),
+    StraightPercentage(u32),
+    Generator(String),
+    EmptyList(&'static str),
+    UncoveredFloor { list: &'static str, floor: usize },
+    UnknownSpawn { prefab: String, name: String },
+    NoHealth(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EmptyRange(field) => write!(f, "maze_generation.{field} must not be empty"),
+            Self::FloorTooSmall { floor, room } => write!(
+                f,
+                "floor_size must be larger than room_size (smallest floor {floor}, largest room {room})"
+            ),
+            Self::FloorBelowMinimum(floor) => write!(
+                f,
+                "floor_size must start from at least {MIN_FLOOR_SIZE} (found {floor})"
+            ),
+            Self::StraightPercentage(value) => {
+                write!(f, "straight_percentage must be at most 100 (found {value})")
+            }
+            Self::Generator(problem) => write!(f, "maze_generation.generator: {problem}"),
+            Self::EmptyList(field) => write!(
+                f,
+                "{field} must not be empty when {field}_total is greater than 0"
+            ),
+            Self::UncoveredFloor { list, floor } => {
+                write!(f, "no entry of {list} can appear on floor {floor}")
+            }
+            Self::UnknownSpawn { prefab, name } => {
+                write!(f, "prefab {prefab} spawns {name}, which is not a configured entity")
+            }
+            Self::NoHealth(name) => write!(f, "{name} must start with more than 0 health"),
+        }
+    }
+}
+
+/// Lato minimo di un piano, sotto il quale i generatori non riescono a creare un piano valido
+/// (vedi Config::validate).
+pub const MIN_FLOOR_SIZE: usize = 5;
+
 /// Configura la generazione del labirinto all'interno del generatore.\
 /// I parametri principali servono ad indicare quanto grande è il piano e quanto grandi sono le stanze.\
 /// *room_placing_attempts* indica quanti tentativi il generatore deve fare prima di smettere di creare stanze.\
 /// *straight_percentage* indica da 0 a 100 quanta percentuale c'è che un corridioio, quando viene generato
 /// rimanga dritto o viri.\
-/// *dead_ends* indica quanti corridoi che non portano a nulla devono esserci alla fine della generazione.
+/// *dead_ends* indica quanti corridoi che non portano a nulla devono esserci alla fine della generazione.\
+/// *min_stairs_distance* indica la lunghezza minima del percorso tra entrata e uscita di ogni piano;
+/// i piani che non la rispettano, o in cui l'uscita non è raggiungibile, vengono rigenerati.\
+/// *prefabs* indica le stanze prefabbricate che possono essere inserite nei piani.\
+/// *generator* indica l'algoritmo con cui viene generata la struttura dei piani; i parametri
+/// precedenti vengono usati solamente dai generatori che ne hanno bisogno.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ConfigMaze {
     pub floor_size: Range<usize>,
@@ -32,6 +244,56 @@ pub struct ConfigMaze {
     pub room_placing_attempts: u32,
     pub straight_percentage: u32,
     pub dead_ends: u32,
+    #[serde(default)]
+    pub min_stairs_distance: usize,
+    #[serde(default)]
+    pub prefabs: Vec<ConfigPrefab>,
+    #[serde(default = "ConfigMaze::default_generator")]
+    pub generator: Box<dyn FloorGenerator>,
+}
+
+impl ConfigMaze {
+    /// Generatore di base dei piani, ovvero quello con stanze e labirinti.
+    pub fn default_generator() -> Box<dyn FloorGenerator> {
+        Box::new(RoomsAndMazes)
+    }
+}
+
+/// Una stanza prefabbricata che si può trovare nei piani del dungeon.\
+/// *chance* indica da 0 a 100 la probabilità che la stanza venga inserita in ogni piano
+/// compreso in *floors*; la stanza viene comunque scartata se non c'è spazio per essa.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConfigPrefab {
+    pub floors: Range<usize>,
+    pub prefab: Prefab,
+    pub chance: u32,
+}
+
+/// Condizioni con cui un giocatore vince la partita; basta che ne raggiunga una.\
+/// *max_turns* indica il numero di turni giocati da sopravvissuto.\
+/// *target_floor* indica il livello del piano da raggiungere.\
+/// *score* indica il punteggio da raggiungere (vedi Statistics::get_score).\
+/// Le condizioni a None vengono ignorate, quindi se la sezione manca dal file di configurazione
+/// non si può vincere; la configurazione di base invece richiede di raggiungere il piano 10.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ConfigVictory {
+    pub max_turns: Option<usize>,
+    pub target_floor: Option<usize>,
+    pub score: Option<u32>,
+}
+
+impl ConfigVictory {
+    /// Indica se le statistiche passate raggiungono almeno una delle condizioni di vittoria.
+    pub fn is_reached(&self, statistics: &Statistics) -> bool {
+        self.max_turns
+            .is_some_and(|turns| statistics.turns >= turns)
+            || self
+                .target_floor
+                .is_some_and(|floor| statistics.deepest_floor >= floor)
+            || self
+                .score
+                .is_some_and(|score| statistics.get_score() >= score)
+    }
 }
 
 /// Un effetto che si può trovare per terra nel dungeon.\
@@ -47,15 +309,39 @@ pub struct ConfigEffect {
     pub priority: u32,
 }
 
+/// Un oggetto che si può trovare per terra nel dungeon e che può essere raccolto.\
+/// La priorità funziona allo stesso modo di ConfigEffect.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConfigItem {
+    pub floors: Range<usize>,
+    pub item: Box<dyn Item>,
+    pub priority: u32,
+}
+
 /// Valori di base per le statistiche di un giocatore.\
-/// Esse verranno utilizzate quando un giocatore verrà creato.
+/// Esse verranno utilizzate quando un giocatore verrà creato.\
+/// *accuracy* indica da 0 a 100 la probabilità di colpire, mentre *growth* indica
+/// come crescono le statistiche ad ogni livello.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ConfigPlayer {
     pub health: i32,
     pub attack: i32,
+    pub defense: i32,
+    pub accuracy: u32,
+    pub growth: Growth,
+}
+
+impl ConfigPlayer {
+    /// Restituisce le statistiche di combattimento iniziali del giocatore.
+    pub fn get_stats(&self) -> CombatStats {
+        CombatStats::new(self.attack, self.defense, self.accuracy)
+    }
 }
 
 /// Una entità che si può trovare in un piano nel dungeon.\
+/// Le statistiche funzionano allo stesso modo di ConfigPlayer, e la crescita indica anche
+/// quanta esperienza ottiene chi la uccide.\
+/// *follow_stairs* indica se l'entità segue un giocatore adiacente quando cambia piano.\
 /// La priorità indica quanto verrà spawnata l'entità in media.\
 /// \
 /// Es. entità A priorità 1 ed entità B con priorità 2\
@@ -68,9 +354,42 @@ pub struct ConfigEntity {
     pub behavior: Box<dyn Behavior>,
     pub health: i32,
     pub attack: i32,
+    pub defense: i32,
+    pub accuracy: u32,
+    pub growth: Growth,
+    pub follow_stairs: bool,
     pub priority: u32,
 }
 
+impl ConfigEntity {
+    /// Restituisce le statistiche di combattimento iniziali dell'entità.
+    pub fn get_stats(&self) -> CombatStats {
+        CombatStats::new(self.attack, self.defense, self.accuracy)
+    }
+}
+
+/// Un piano con un boss che si può trovare nel dungeon.\
+/// Il boss viene inserito vicino all'uscita di un piano ogni *every* piani (quindi ai livelli
+/// every-1, 2*every-1, ...) purchè il livello sia compreso in *floors*, e l'uscita del piano
+/// rimane chiusa finchè esso non muore.\
+/// *minions* indica le entità che vengono inserite vicino al boss insieme ad esso.\
+/// Per il boss e per i suoi seguaci i campi *floors* e *priority* di ConfigEntity non vengono
+/// utilizzati, inoltre il boss non segue mai i giocatori sulle scale.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConfigBoss {
+    pub floors: Range<usize>,
+    pub every: usize,
+    pub boss: ConfigEntity,
+    pub minions: Vec<ConfigEntity>,
+}
+
+impl ConfigBoss {
+    /// Indica se il piano del livello passato deve avere questo boss.
+    pub fn is_boss_floor(&self, level: usize) -> bool {
+        self.every > 0 && self.floors.contains(&level) && (level + 1).is_multiple_of(self.every)
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -81,6 +400,9 @@ impl Default for Config {
                 room_placing_attempts: 10,
                 straight_percentage: 90,
                 dead_ends: 0,
+                min_stairs_distance: 10,
+                prefabs: vec![],
+                generator: ConfigMaze::default_generator(),
             },
             effects: vec![
                 ConfigEffect {
@@ -98,20 +420,133 @@ impl Default for Config {
                     floors: 0..255,
                     priority: 10,
                 },
+                ConfigEffect {
+                    effect: Box::new(Poison {
+                        damage: 2,
+                        duration: 5,
+                    }),
+                    floors: 1..255,
+                    priority: 5,
+                },
+                ConfigEffect {
+                    effect: Box::new(Teleport),
+                    floors: 2..255,
+                    priority: 3,
+                },
             ],
             effects_total: 45,
-            entities: vec![ConfigEntity {
+            items: vec![
+                ConfigItem {
+                    item: Box::new(Potion::new(
+                        "Health potion".to_string(),
+                        Box::new(InstantDamage(-30)),
+                    )),
+                    floors: 0..255,
+                    priority: 1,
+                },
+                ConfigItem {
+                    item: Box::new(Potion::new(
+                        "Regeneration potion".to_string(),
+                        Box::new(Regeneration {
+                            heal: 5,
+                            duration: 10,
+                        }),
+                    )),
+                    floors: 0..255,
+                    priority: 2,
+                },
+                ConfigItem {
+                    item: Box::new(Potion::new(
+                        "Haste potion".to_string(),
+                        Box::new(Haste { duration: 10 }),
+                    )),
+                    floors: 1..255,
+                    priority: 2,
+                },
+                ConfigItem {
+                    item: Box::new(Weapon::new("Sword".to_string(), 5)),
+                    floors: 1..255,
+                    priority: 3,
+                },
+            ],
+            items_total: 4,
+            entities: vec![
+                ConfigEntity {
+                    floors: 0..255,
+                    name: "Basic enemy".to_string(),
+                    behavior: Box::new(RandomMovement::new()),
+                    health: 10,
+                    attack: 10,
+                    defense: 0,
+                    accuracy: 80,
+                    growth: Growth::default(),
+                    follow_stairs: false,
+                    priority: 1,
+                },
+                ConfigEntity {
+                    floors: 1..255,
+                    name: "Hunter".to_string(),
+                    behavior: Box::new(Chaser::new(6)),
+                    health: 15,
+                    attack: 5,
+                    defense: 1,
+                    accuracy: 90,
+                    growth: Growth {
+                        reward: 20,
+                        ..Default::default()
+                    },
+                    follow_stairs: true,
+                    priority: 2,
+                },
+            ],
+            entities_total: 10,
+            bosses: vec![ConfigBoss {
                 floors: 0..255,
-                name: "Basic enemy".to_string(),
-                behavior: Box::new(RandomMovement::new()),
-                health: 10,
-                attack: 10,
-                priority: 1,
+                every: 5,
+                boss: ConfigEntity {
+                    floors: 0..0,
+                    name: "Warden".to_string(),
+                    behavior: Box::new(Chaser::new(8)),
+                    health: 80,
+                    attack: 15,
+                    defense: 3,
+                    accuracy: 85,
+                    growth: Growth {
+                        reward: 100,
+                        ..Default::default()
+                    },
+                    follow_stairs: false,
+                    priority: 0,
+                },
+                minions: vec![
+                    ConfigEntity {
+                        floors: 0..0,
+                        name: "Guard".to_string(),
+                        behavior: Box::new(Chaser::new(4)),
+                        health: 20,
+                        attack: 8,
+                        defense: 1,
+                        accuracy: 80,
+                        growth: Growth {
+                            reward: 15,
+                            ..Default::default()
+                        },
+                        follow_stairs: false,
+                        priority: 0,
+                    };
+                    2
+                ],
             }],
-            entities_total: 10,
+            victory: ConfigVictory {
+                target_floor: Some(10),
+                ..Default::default()
+            },
             player_stats: ConfigPlayer {
                 health: 100,
                 attack: 10,
+                defense: 2,
+                accuracy: 90,
+                growth: Growth::default(),
             },
         }
     }
@@ -1,11 +1,19 @@
 use super::{
     cell::{Cell, Effect},
-    floor::{Floor, FloorView},
+    event::EventKind,
+    floor::{Floor, FloorView, PathOptions, SIGHT_RADIUS},
+    item::Item,
+    status::{ActiveStatus, Modifiers, Stacking, Status},
 };
 use dyn_clone::{clone_trait_object, DynClone};
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
 use serde::{Deserialize, Serialize};
-use std::{collections::VecDeque, fmt::Display, mem};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::Display,
+    mem,
+};
 
 /// Tupla nominata Position in modo che nel codice sia più chiaro a cosa serve.\
 /// È molto più facile capire a colpo d'occhio Position rispetto a (usize, usize)\
@@ -14,6 +22,14 @@ use std::{collections::VecDeque, fmt::Display, mem};
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug, Deserialize, Serialize)]
 pub struct Position(pub usize, pub usize);
 
+impl Position {
+    /// Calcola la distanza tra due posizioni muovendosi solamente nelle 4 direzioni.\
+    /// https://en.wikipedia.org/wiki/Taxicab_geometry
+    pub fn distance(&self, other: &Position) -> usize {
+        self.0.abs_diff(other.0) + self.1.abs_diff(other.1)
+    }
+}
+
 /// Indica la direzione dove una entità sta guardando.\
 /// È possibile anche non guardare in nessuna direzione tramite None.
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Default, Debug, Deserialize, Serialize)]
@@ -53,6 +69,29 @@ impl Direction {
         };
         pos
     }
+    /// Restituisce tutte le direzioni in cui ci si può muovere, escludendo quindi None.
+    pub fn all() -> [Self; 4] {
+        [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+    }
+    /// Restituisce la direzione da prendere per andare dalla prima posizione alla seconda.\
+    /// Le due posizioni devono essere adiacenti, altrimenti viene ritornato None.
+    pub fn towards(from: &Position, to: &Position) -> Self {
+        match (
+            to.0 as isize - from.0 as isize,
+            to.1 as isize - from.1 as isize,
+        ) {
+            (0, 1) => Direction::Up,
+            (0, -1) => Direction::Down,
+            (-1, 0) => Direction::Left,
+            (1, 0) => Direction::Right,
+            _ => Direction::None,
+        }
+    }
     /// Restituisce una direzione casuale a partire da un generatore.\
     /// La direzione viene generata con una distribuzione uniforme, ovvero non
     /// c'è una direzione preferita o con più probabilità.
@@ -84,39 +123,224 @@ impl Display for Direction {
     }
 }
 
-/// Rappresenta una entità all'interno del dungeon.
+/// Statistiche raccolte durante la partita da una entità.\
+/// *turns* indica quanti turni l'entità ha giocato.\
+/// *kills* indica quante entità sono state uccise da essa.\
+/// *deepest_floor* indica il piano più profondo raggiunto.\
+/// *items* indica quanti oggetti sono stati raccolti.\
+/// *damage_taken* indica il danno subito suddiviso per tipo di effetto (o Attack se da un'altra entità).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Statistics {
+    pub turns: usize,
+    pub kills: usize,
+    pub deepest_floor: usize,
+    pub damage_taken: BTreeMap<String, i32>,
+    #[serde(default)]
+    pub items: usize,
+}
+
+impl Statistics {
+    /// Aggiunge il danno subito al tipo indicato.\
+    /// Nel caso in cui il danno non sia positivo (es. una cura), esso viene ignorato.
+    pub fn add_damage(&mut self, source: &str, damage: i32) {
+        if damage > 0 {
+            *self.damage_taken.entry(source.to_string()).or_default() += damage;
+        }
+    }
+
+    /// Restituisce il danno totale subito.
+    pub fn get_total_damage(&self) -> i32 {
+        self.damage_taken.values().sum()
+    }
+
+    /// Restituisce il punteggio ottenuto fino ad ora.\
+    /// Ogni piano raggiunto vale 100 punti, ogni entità uccisa 10, ogni oggetto raccolto 5
+    /// e ogni turno giocato 1.
+    pub fn get_score(&self) -> u32 {
+        let score = self.deepest_floor * 100 + self.kills * 10 + self.items * 5 + self.turns;
+        score.try_into().unwrap_or(u32::MAX)
+    }
+}
+
+/// Statistiche di combattimento di una entità.\
+/// *attack* indica il danno base inflitto con un attacco.\
+/// *defense* indica di quanto viene ridotto ogni danno subito da un attacco.\
+/// *accuracy* indica da 0 a 100 la probabilità di colpire un'altra entità.\
+/// *experience* indica l'esperienza accumulata nel livello attuale.\
+/// *level* indica il livello attuale dell'entità (a partire da 1).
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CombatStats {
+    pub attack: i32,
+    pub defense: i32,
+    pub accuracy: u32,
+    pub experience: u32,
+    pub level: u32,
+}
+
+impl CombatStats {
+    /// Crea delle statistiche di livello 1 senza esperienza.
+    pub fn new(attack: i32, defense: i32, accuracy: u32) -> Self {
+        Self {
+            attack,
+            defense,
+            accuracy: accuracy.min(100),
+            experience: 0,
+            level: 1,
+        }
+    }
+}
+
+/// Indica come crescono le statistiche di una entità ad ogni aumento di livello.\
+/// *experience* indica l'esperienza necessaria per passare dal livello 1 al 2, mentre
+/// *multiplier* indica di quanto aumenta l'esperienza necessaria per ogni livello successivo.\
+/// *health*, *attack*, *defense* e *accuracy* indicano quanto aumentano le rispettive statistiche.\
+/// *reward* indica quanta esperienza ottiene chi uccide l'entità, moltiplicata per il suo livello.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Growth {
+    pub experience: u32,
+    pub multiplier: f32,
+    pub health: i32,
+    pub attack: i32,
+    pub defense: i32,
+    pub accuracy: u32,
+    pub reward: u32,
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Self {
+            experience: 100,
+            multiplier: 1.5,
+            health: 10,
+            attack: 2,
+            defense: 1,
+            accuracy: 1,
+            reward: 10,
+        }
+    }
+}
+
+impl Growth {
+    /// Restituisce l'esperienza necessaria per passare dal livello indicato al successivo.
+    pub fn experience_for(&self, level: u32) -> u32 {
+        let exponent = level.saturating_sub(1) as i32;
+        (self.experience as f32 * self.multiplier.powi(exponent)).max(1.0) as u32
+    }
+}
+
+/// Risultato dell'update di una entità.\
+/// In ogni caso viene restituita l'entità in modo che si possa leggere il suo stato finale,
+/// come ad esempio le statistiche raccolte durante la partita.
+#[derive(Debug)]
+pub enum EntityUpdate {
+    /// L'entità è ancora in gioco.
+    Alive(Entity),
+    /// L'entità è morta durante l'update e deve essere rimossa dal gioco.
+    Dead(Entity),
+    /// Il behavior non ha deciso nessuna azione, quindi l'entità deve essere rimossa dal gioco.
+    Removed(Entity),
+}
+
+impl EntityUpdate {
+    /// Restituisce l'entità solamente nel caso in cui sia ancora in gioco.
+    pub fn alive(self) -> Option<Entity> {
+        match self {
+            EntityUpdate::Alive(entity) => Some(entity),
+            _ => None,
+        }
+    }
+}
+
+/// Numero massimo di oggetti che una entità può tenere nell'inventario.
+pub const INVENTORY_SIZE: usize = 9;
+
+/// Rappresenta una entità all'interno del dungeon.\
+/// *follow_stairs* indica se l'entità segue un giocatore adiacente quando esso cambia piano.\
+/// *boss* indica se l'entità è il boss del piano in cui si trova (vedi Floor::add_boss).
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Entity {
     name: String,
     effects: VecDeque<Box<dyn Effect>>,
+    #[serde(default)]
+    statuses: Vec<ActiveStatus>,
     behavior: Option<Box<dyn Behavior>>,
+    #[serde(default)]
+    explored: HashMap<usize, HashSet<Position>>,
+    #[serde(default)]
+    inventory: Vec<Box<dyn Item>>,
+    #[serde(default = "Entity::default_rng")]
+    rng: Pcg32,
+    #[serde(default)]
+    statistics: Statistics,
     pub buffer: Action,
     pub position: Position,
     pub direction: Direction,
+    #[serde(default)]
+    pub follow_stairs: bool,
+    #[serde(default)]
+    pub boss: bool,
     health_max: i32,
     health: i32,
-    attack: i32,
+    stats: CombatStats,
+    #[serde(default)]
+    growth: Growth,
 }
 
 impl Entity {
     /// Costruttore che crea una nuova entità a partire dal suo nome, vita, danno di attacco,
     /// il decisore che permette di muoversi (giocatore o IA) e il piano in cui si trova.\
     /// La posizione sarà all'entrata del piano (o in una cella vicina nel caso ci siano altre entità sopra),
-    /// non avrà effetti, azioni o una direzione in particolare.
+    /// non avrà effetti, azioni o una direzione in particolare.\
+    /// L'entità non avrà difesa, colpirà sempre e crescerà secondo la crescita di default.
     pub fn new(name: String, health: i32, attack: i32, behavior: Box<dyn Behavior>) -> Self {
+        let stats = CombatStats::new(attack, 0, 100);
+        Self::new_with(name, health, stats, Growth::default(), behavior)
+    }
+
+    /// Costruttore che crea una nuova entità a partire dal suo nome, vita, statistiche di
+    /// combattimento, crescita e il decisore che permette di muoversi.\
+    /// Per il resto l'entità è identica a quella creata tramite new.
+    pub fn new_with(
+        name: String,
+        health: i32,
+        stats: CombatStats,
+        growth: Growth,
+        behavior: Box<dyn Behavior>,
+    ) -> Self {
         Self {
             name,
             behavior: Some(behavior),
             position: Position(0, 0),
-            attack,
+            stats,
+            growth,
             health,
             health_max: health,
             buffer: Action::DoNothing,
             effects: VecDeque::new(),
+            statuses: vec![],
             direction: Direction::None,
+            follow_stairs: false,
+            boss: false,
+            explored: HashMap::new(),
+            inventory: vec![],
+            rng: Self::default_rng(),
+            statistics: Statistics::default(),
         }
     }
 
+    /// Generatore di numeri casuali di base per una entità appena creata.
+    fn default_rng() -> Pcg32 {
+        Pcg32::seed_from_u64(0)
+    }
+
+    /// Reimposta il generatore di numeri casuali dell'entità a partire dal seed indicato.\
+    /// Il generatore viene passato al behavior in modo che esso possa prendere decisioni
+    /// casuali ma ripetibili, dato che viene anche serializzato insieme all'entità.\
+    /// Idealmente il seed deve essere derivato dal generatore del piano.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = Pcg32::seed_from_u64(seed);
+    }
+
     /// Aggiunge l'effetto passato in input all'entità.\
     /// Questo non viene calcolato immediatamente, ma solo quando si chiama la
     /// funzione update.\
@@ -133,6 +357,187 @@ impl Entity {
         self.effects.iter()
     }
 
+    /// Aggiunge lo stato passato all'entità, che rimarrà attivo per la sua durata.\
+    /// Nel caso in cui l'entità abbia già uno stato dello stesso tipo, viene seguita la
+    /// politica di Stacking indicata dallo stato; l'hook on_apply viene chiamato solamente
+    /// quando lo stato viene aggiunto come nuova istanza.\
+    /// Gli stati con durata 0 vengono ignorati.
+    pub fn add_status(&mut self, status: Box<dyn Status>, floor: &mut Floor) {
+        let duration = status.get_duration();
+        if duration == 0 {
+            return;
+        }
+
+        let name = status.typetag_name();
+        let active = self
+            .statuses
+            .iter_mut()
+            .find(|active| active.status.typetag_name() == name);
+        match (active, status.get_stacking()) {
+            (Some(active), Stacking::Refresh) => {
+                active.remaining = active.remaining.max(duration);
+                active.status = status;
+            }
+            (Some(active), Stacking::Extend) => active.remaining += duration,
+            (Some(_), Stacking::Ignore) => {}
+            (Some(_), Stacking::Stack) | (None, _) => {
+                status.on_apply(self, floor);
+                self.statuses.push(ActiveStatus {
+                    status,
+                    remaining: duration,
+                });
+            }
+        }
+    }
+
+    /// Permette di vedere tutti gli stati attivi in questo momento sull'entità,
+    /// nell'ordine in cui sono stati applicati.
+    pub fn get_statuses(&self) -> impl Iterator<Item = &ActiveStatus> {
+        self.statuses.iter()
+    }
+
+    /// Restituisce la somma delle modifiche di tutti gli stati attivi sull'entità.
+    pub fn get_modifiers(&self) -> Modifiers {
+        self.statuses
+            .iter()
+            .map(|active| active.status.get_modifiers())
+            .fold(Modifiers::default(), Modifiers::merge)
+    }
+
+    /// Restituisce il raggio di visione dell'entità, modificato dagli stati attivi.\
+    /// Il raggio è sempre di almeno 1, in modo che l'entità veda almeno le celle adiacenti.
+    pub fn get_sight_radius(&self) -> usize {
+        let sight = self.get_modifiers().sight as isize;
+        SIGHT_RADIUS.saturating_add_signed(sight).max(1)
+    }
+
+    /// Restituisce quante azioni l'entità può fare nel turno attuale.\
+    /// Di base è una sola, ma gli stati attivi possono aggiungerne altre, farne saltare
+    /// alcuni turni o impedire del tutto di agire.
+    pub fn get_actions(&self) -> usize {
+        let modifiers = self.get_modifiers();
+        match modifiers.speed {
+            _ if modifiers.stunned => 0,
+            speed if speed >= 0 => 1 + speed as usize,
+            speed => self
+                .statistics
+                .turns
+                .is_multiple_of(1 + speed.unsigned_abs() as usize) as usize,
+        }
+    }
+
+    /// Permette di vedere tutti gli oggetti presenti nell'inventario dell'entità.\
+    /// L'ordine degli oggetti è quello con cui sono stati raccolti ed è lo stesso
+    /// indice da utilizzare nelle azioni Drop e Use.
+    pub fn get_inventory(&self) -> impl Iterator<Item = &Box<dyn Item>> {
+        self.inventory.iter()
+    }
+
+    /// Aggiunge un oggetto all'inventario dell'entità.\
+    /// Nel caso in cui l'inventario sia pieno, l'oggetto viene restituito.
+    pub fn add_item(&mut self, item: Box<dyn Item>) -> Result<(), Box<dyn Item>> {
+        if self.inventory.len() >= INVENTORY_SIZE {
+            Err(item)
+        } else {
+            self.inventory.push(item);
+            Ok(())
+        }
+    }
+
+    /// Rimuove l'oggetto all'indice indicato dall'inventario e lo restituisce.\
+    /// Nel caso in cui l'indice non sia valido viene restituito None.
+    pub fn remove_item(&mut self, index: usize) -> Option<Box<dyn Item>> {
+        (index < self.inventory.len()).then(|| self.inventory.remove(index))
+    }
+
+    /// Restituisce il valore dell'attacco dell'entità.\
+    /// Esso comprende anche il bonus dell'arma migliore presente nell'inventario
+    /// e quello degli stati attivi.
+    pub fn get_attack(&self) -> i32 {
+        let bonus = self.inventory.iter().map(|item| item.attack_bonus()).max();
+        self.stats.attack + bonus.unwrap_or(0).max(0) + self.get_modifiers().attack
+    }
+
+    /// Restituisce le statistiche di combattimento dell'entità.
+    pub fn get_stats(&self) -> &CombatStats {
+        &self.stats
+    }
+
+    /// Restituisce la crescita delle statistiche dell'entità.
+    pub fn get_growth(&self) -> &Growth {
+        &self.growth
+    }
+
+    /// Restituisce l'esperienza che ottiene chi uccide questa entità.
+    pub fn get_reward(&self) -> u32 {
+        self.growth.reward * self.stats.level
+    }
+
+    /// Aggiunge l'esperienza indicata all'entità.\
+    /// Nel caso in cui l'esperienza superi quella necessaria per il livello attuale,
+    /// l'entità aumenta di livello e le sue statistiche crescono secondo la sua crescita.\
+    /// Con molta esperienza si possono guadagnare più livelli alla volta.\
+    /// Restituisce il numero di livelli guadagnati.
+    pub fn add_experience(&mut self, experience: u32) -> u32 {
+        let level = self.stats.level;
+        self.stats.experience += experience;
+        loop {
+            let needed = self.growth.experience_for(self.stats.level);
+            if self.stats.experience < needed {
+                return self.stats.level - level;
+            }
+
+            self.stats.experience -= needed;
+            self.stats.level += 1;
+            self.stats.attack += self.growth.attack;
+            self.stats.defense += self.growth.defense;
+            self.stats.accuracy = (self.stats.accuracy + self.growth.accuracy).min(100);
+            self.health_max += self.growth.health;
+            self.apply_damage(-self.growth.health);
+        }
+    }
+
+    /// Calcola il danno subito da un attacco della potenza indicata.\
+    /// La difesa riduce il danno, ma un attacco che va a segno fa sempre almeno 1 danno.
+    pub fn get_damage_from(&self, attack: i32) -> i32 {
+        (attack - self.stats.defense).max(1)
+    }
+
+    /// Restituisce le statistiche raccolte dall'entità durante la partita.
+    pub fn get_statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// Sostituisce il behavior dell'entità con quello indicato.\
+    /// Utile, ad esempio, per ricollegare un giocatore alla sua interfaccia dopo aver
+    /// caricato una partita.
+    pub fn set_behavior(&mut self, behavior: Box<dyn Behavior>) {
+        self.behavior = Some(behavior);
+    }
+
+    /// Restituisce le statistiche raccolte dall'entità in modo da poterle modificare.
+    pub fn get_statistics_mut(&mut self) -> &mut Statistics {
+        &mut self.statistics
+    }
+
+    /// Memorizza tutte le celle che l'entità riesce a vedere in questo momento nel piano indicato.\
+    /// Le celle memorizzate rimangono tali anche quando non sono più visibili, in modo da
+    /// poter mostrare le zone del piano già esplorate.
+    pub fn explore(&mut self, floor: &Floor) {
+        let visible = floor.get_visible_from(&self.position, self.get_sight_radius());
+        self.explored
+            .entry(floor.get_level())
+            .or_default()
+            .extend(visible);
+    }
+
+    /// Indica se l'entità ha già visto la posizione indicata nel piano di livello passato.
+    pub fn has_explored(&self, level: usize, position: &Position) -> bool {
+        self.explored
+            .get(&level)
+            .is_some_and(|explored| explored.contains(position))
+    }
+
     /// Indica se l'entità è considerata ancora in gioco o meno.\
     /// Per far si che l'entità non sia più in gioco bisobna far arrivare la vita a 0.
     /// Nota: una entità con vita negativa è considerata "viva"
@@ -175,64 +580,138 @@ impl Entity {
     /// e l' entità smetterà di esistere.\
     /// Nel caso in cui l'entità non riesca a fare l'update viene ritornato None.\
     /// Cio significa che l'entità verrà rimossa dal gioco.
-    pub fn update(mut self, floor: &mut Floor) -> Option<Self> {
+    pub fn update(self, floor: &mut Floor) -> Option<Self> {
+        self.update_with_actions(floor).0.alive()
+    }
+
+    /// Funziona esattamente come update, ma restituisce anche l'entità nel caso in cui
+    /// sia morta o rimossa e le azioni decise dal behavior.\
+    /// Le azioni restituite sono quelle generate dal behavior prima di applicare un eventuale buffer;
+    /// di norma è una sola, ma gli stati attivi possono farne fare di più o nessuna.\
+    /// Il behavior viene aggiornato solamente prima di ogni azione richiesta, quindi nei turni
+    /// in cui l'entità non può agire non riceve nessun aggiornamento.\
+    /// Utile nel caso in cui si voglia registrare le azioni fatte dall'entità.
+    pub fn update_with_actions(mut self, floor: &mut Floor) -> (EntityUpdate, Vec<Action>) {
         let mut behavior = mem::take(&mut self.behavior).unwrap();
+        let mut actions = vec![];
 
         if !self.is_alive() {
-            return self.die(behavior, floor);
+            return (self.die(behavior, floor), actions);
         }
 
-        behavior.update(floor.get_limited_view_floor(&self));
-        let action = self.compute_action(&mut behavior, floor);
-        if action.is_none() {
-            return None;
-        }
+        let start = self.position;
+        for i in 0..self.get_actions() {
+            if i > 0 {
+                if self.get_actions() == 0 {
+                    break;
+                }
+                self.explore(floor);
+            }
+            let mut rng = self.rng.clone();
+            behavior.update(floor.get_limited_view_floor(&self), &mut rng);
+            self.rng = rng;
 
-        if !self.is_alive() {
-            return self.die(behavior, floor);
+            match self.compute_action(&mut behavior, floor) {
+                Some(action) => actions.push(action),
+                None => return (EntityUpdate::Removed(self), actions),
+            }
+            if !self.is_alive() {
+                self.statistics.turns += 1;
+                return (self.die(behavior, floor), actions);
+            }
+            if self.position != start && floor.get_stairs_at(&self.position).is_some() {
+                break;
+            }
         }
 
+        self.statistics.turns += 1;
+        self.compute_statuses(floor);
         self.compute_effects(floor);
         if !self.is_alive() {
-            return self.die(behavior, floor);
+            return (self.die(behavior, floor), actions);
         }
 
         self.behavior = Some(behavior);
-        Some(self)
+        (EntityUpdate::Alive(self), actions)
+    }
+
+    /// Notifica al behavior dell'entità che essa ha vinto la partita e lo rimuove,
+    /// dato che l'entità non giocherà più.
+    pub fn win(&mut self, floor: &Floor) {
+        if let Some(mut behavior) = self.behavior.take() {
+            behavior.on_victory(floor.get_limited_view_floor(self));
+        }
     }
 
     /// metodo usato per la rimozione dell' entità e del suo behavior
-    fn die(self, mut behavior: Box<dyn Behavior>, floor: &Floor) -> Option<Self> {
+    fn die(self, mut behavior: Box<dyn Behavior>, floor: &Floor) -> EntityUpdate {
         let view = floor.get_limited_view_floor(&self);
         behavior.on_death(view);
-        None
+        EntityUpdate::Dead(self)
     }
 
-    /// calcola gli effetti e li applica all'entità.
+    /// calcola gli effetti e li applica all'entità.\
+    /// Ogni variazione di vita e l'eventuale morte vengono registrate come eventi nel piano.
     fn compute_effects(&mut self, floor: &mut Floor) {
         let total = self.effects.len(); // len could change
         for _ in 0..total {
             if let Some(effect) = self.effects.pop_front() {
+                let health = self.health;
                 effect.apply_to(self, floor);
+                self.record_damage(effect.typetag_name(), health, floor);
             }
         }
     }
+
+    /// fa passare un turno a tutti gli stati attivi, rimuovendo quelli terminati.\
+    /// Gli stati aggiunti dagli hook vengono messi in fondo e partiranno dal turno successivo.
+    fn compute_statuses(&mut self, floor: &mut Floor) {
+        let mut remaining = vec![];
+        for mut active in mem::take(&mut self.statuses) {
+            let health = self.health;
+            active.status.on_tick(self, floor);
+            active.remaining -= 1;
+            if active.remaining == 0 {
+                active.status.on_expire(self, floor);
+            }
+            self.record_damage(active.status.typetag_name(), health, floor);
+            if active.remaining > 0 {
+                remaining.push(active);
+            }
+        }
+        remaining.append(&mut self.statuses);
+        self.statuses = remaining;
+    }
+
+    /// registra la variazione di vita rispetto a quella indicata come causata dalla sorgente.\
+    /// La variazione e l'eventuale morte vengono registrate come eventi nel piano.
+    fn record_damage(&mut self, source: &str, health: i32, floor: &mut Floor) {
+        let damage = health - self.health;
+        self.statistics.add_damage(source, damage);
+
+        if damage != 0 {
+            let kind = EventKind::Damage(source.to_string(), damage);
+            floor.add_event(None, self.name.clone(), kind, self.position);
+        }
+        if health != 0 && !self.is_alive() {
+            floor.add_event(None, self.name.clone(), EventKind::Death, self.position);
+        }
+    }
     /// prende una decisione e applica l'azione da fare
-    /// L'azione compiuta viene restituita, altrimenti None
+    /// L'azione decisa dal behavior viene restituita, altrimenti None
     fn compute_action(
         &mut self,
         behavior: &mut Box<dyn Behavior>,
         floor: &mut Floor,
     ) -> Option<Action> {
-        let action = behavior.get_next_action(self)?;
+        let decided = behavior.get_next_action(self)?;
         let action = match self.buffer {
-            Action::DoNothing => action,
+            Action::DoNothing => decided.clone(),
             _ => mem::replace(&mut self.buffer, Action::DoNothing),
         };
 
-        let result = Some(action.clone());
         action.apply(self, floor);
-        result
+        Some(decided)
     }
 }
 
@@ -247,18 +726,22 @@ impl Display for Entity {
 
         write!(
             f,
-            "{}: {} {}{:4}/{:4}",
-            self.name, self.direction, health_bar, self.health, self.health_max
+            "{} Lv.{}: {} {}{:4}/{:4}",
+            self.name, self.stats.level, self.direction, health_bar, self.health, self.health_max
         )
     }
 }
 
 /// Azione che una qualsiasi entità può fare.
-/// L'azione DoNothing permette all'entità di saltare il turno nel caso in cui sia utile.
-#[derive(Clone, Default, Debug, Deserialize, Serialize)]
+/// L'azione DoNothing permette all'entità di saltare il turno nel caso in cui sia utile.\
+/// Le azioni Drop e Use indicano l'indice dell'oggetto nell'inventario.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Action {
     Move(Direction),
     Attack(Direction),
+    PickUp,
+    Drop(usize),
+    Use(usize),
     #[default]
     DoNothing,
 }
@@ -269,7 +752,10 @@ impl Action {
     /// Per ogni tipo di azione l'entità viene modificata opportunamente.\
     /// \
     /// Es. Move(Up) sposterà l'entità da una posizione (x,y) -> (x,y+1)\
-    /// e applicherà qualunque effetto che si trovi sulla cella di destinazione
+    /// e applicherà qualunque effetto che si trovi sulla cella di destinazione\
+    /// Es. Attack(Up) colpirà l'entità che si trova sopra con una probabilità pari alla precisione,
+    /// infliggendo un danno ridotto dalla sua difesa; se la uccide guadagna esperienza\
+    /// Es. PickUp raccoglierà l'oggetto che si trova sotto l'entità se l'inventario non è pieno
     pub fn apply(self, entity: &mut Entity, floor: &mut Floor) {
         match self {
             Action::DoNothing => {}
@@ -278,14 +764,78 @@ impl Action {
                 entity.direction = direction;
 
                 let cell = floor.get_cell_mut(&entity.position);
+                let effect = match cell {
+                    Cell::Special(effect) => Some(effect.typetag_name().to_string()),
+                    _ => None,
+                };
                 cell.entity_over(entity);
+                if let Some(effect) = effect {
+                    let kind = EventKind::Effect(effect);
+                    floor.add_event(None, entity.name.clone(), kind, entity.position);
+                }
             }
             Action::Attack(direction) => {
                 let mut pos = entity.position;
                 direction.move_from(&mut pos);
 
-                if let Some(other) = floor.get_entity_at(&pos) {
-                    other.apply_damage(entity.attack);
+                let target = floor.get_entity_at(&pos).map(|other| other.name.clone());
+                let Some(target) = target else {
+                    return;
+                };
+                let source = Some(entity.name.clone());
+                if floor.get_rng().gen_range(0..100) >= entity.stats.accuracy {
+                    floor.add_event(source, target, EventKind::Miss, pos);
+                    return;
+                }
+
+                let other = floor.get_entity_at(&pos).unwrap();
+                let health = other.health;
+                other.apply_damage(other.get_damage_from(entity.get_attack()));
+                let damage = health - other.health;
+                let killed = health != 0 && !other.is_alive();
+                let reward = other.get_reward();
+                other.statistics.add_damage("Attack", damage);
+
+                let kind = EventKind::Attack(damage);
+                floor.add_event(source.clone(), target.clone(), kind, pos);
+                if killed {
+                    entity.statistics.kills += 1;
+                    floor.add_event(source, target, EventKind::Death, pos);
+                    if entity.add_experience(reward) > 0 {
+                        let kind = EventKind::LevelUp(entity.stats.level);
+                        floor.add_event(None, entity.name.clone(), kind, entity.position);
+                    }
+                }
+            }
+            Action::PickUp => {
+                let cell = floor.get_cell_mut(&entity.position);
+                if matches!(cell, Cell::Item(_)) && entity.inventory.len() < INVENTORY_SIZE {
+                    if let Cell::Item(item) = mem::replace(cell, Cell::Empty) {
+                        let kind = EventKind::PickUp(item.get_name());
+                        entity.inventory.push(item);
+                        entity.statistics.items += 1;
+                        floor.add_event(None, entity.name.clone(), kind, entity.position);
+                    }
+                }
+            }
+            Action::Drop(index) => {
+                let cell = floor.get_cell_mut(&entity.position);
+                if let Cell::Empty = cell {
+                    if let Some(item) = entity.remove_item(index) {
+                        let kind = EventKind::Drop(item.get_name());
+                        *cell = Cell::Item(item);
+                        floor.add_event(None, entity.name.clone(), kind, entity.position);
+                    }
+                }
+            }
+            Action::Use(index) => {
+                if let Some(item) = entity.remove_item(index) {
+                    let kind = EventKind::Use(item.get_name());
+                    floor.add_event(None, entity.name.clone(), kind, entity.position);
+                    if !item.use_on(entity, floor) {
+                        let index = index.min(entity.inventory.len());
+                        entity.inventory.insert(index, item);
+                    }
                 }
             }
         }
@@ -312,13 +862,22 @@ pub trait Behavior: DynClone + core::fmt::Debug {
     /// piano semplice, avente solo delle informazioni parziali.\
     /// Questo serve a mostrare eventualmente delle possibili informazioni all'utente
     /// o di registrare dei valori per l'algoritmo di generazione delle azioni.\
+    /// Il generatore passato appartiene all'entità ed è l'unico che deve essere usato per
+    /// eventuali decisioni casuali, in modo che la partita sia ripetibile a partire dal seed.\
+    /// Viene chiamata solamente subito prima di get_next_action, quindi non nei turni in cui
+    /// l'entità non può agire (ad esempio perchè stordita).\
     /// Non è necessario implementarla.
-    fn update(&mut self, _view: FloorView) {}
+    fn update(&mut self, _view: FloorView, _rng: &mut Pcg32) {}
     /// Funzione che viene richiamata quando l'entità muore.\
     /// I parametri servono a far vedere un'ultima volta i dati del piano corrente all'entità
     /// in modo che possa eventualmente fare ulteriori calcoli.\
     /// Non è necessario implementarla.
     fn on_death(&mut self, _view: FloorView) {}
+    /// Funzione che viene richiamata quando l'entità vince la partita, dopodichè essa
+    /// viene rimossa dal gioco.\
+    /// Come per on_death, serve a far vedere un'ultima volta i dati del piano corrente.\
+    /// Non è necessario implementarla.
+    fn on_victory(&mut self, _view: FloorView) {}
     /// Genera un'azione che poi verrà usata per l'entità associata.\
     /// L'azione può essere generata in qualunque modo: casuale, sempre la stessa,
     /// tramite interazione con console, o tramite una connessione ad un client.\
@@ -355,10 +914,9 @@ impl RandomMovement {
 }
 #[typetag::serde]
 impl Behavior for RandomMovement {
-    fn update(&mut self, view: FloorView) {
-        let mut pos = view.entity.position.clone();
-        let mut rng = rand::rngs::ThreadRng::default();
-        let dir = Direction::random(&mut rng);
+    fn update(&mut self, view: FloorView, rng: &mut Pcg32) {
+        let mut pos = view.entity.position;
+        let dir = Direction::random(rng);
 
         dir.move_from(&mut pos);
         if let Cell::Empty = view.floor.get_cell(&pos) {
@@ -369,3 +927,54 @@ impl Behavior for RandomMovement {
         Some(mem::take(&mut self.action))
     }
 }
+
+/// Implementazione di un comportamento che insegue i giocatori.\
+/// L'entità cerca il giocatore più vicino entro il raggio indicato e che riesce a vedere,
+/// dopodichè si muove verso di esso tramite il percorso più breve evitando le celle speciali.\
+/// Una volta che il giocatore è adiacente, l'entità lo attacca.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chaser {
+    radius: usize,
+    action: Action,
+}
+impl Chaser {
+    pub fn new(radius: usize) -> Self {
+        let action = Action::default();
+        Self { radius, action }
+    }
+}
+#[typetag::serde]
+impl Behavior for Chaser {
+    fn update(&mut self, view: FloorView, _rng: &mut Pcg32) {
+        let position = view.entity.position;
+        let target = view
+            .floor
+            .get_players()
+            .filter(|player| player.is_alive())
+            .map(|player| player.position)
+            .filter(|pos| pos.distance(&position) <= self.radius && view.is_visible(pos))
+            .min_by_key(|pos| (pos.distance(&position), *pos));
+
+        self.action = match target {
+            Some(target) if target.distance(&position) == 1 => {
+                Action::Attack(Direction::towards(&position, &target))
+            }
+            Some(target) => {
+                let options = PathOptions {
+                    avoid_special: true,
+                    avoid_entities: true,
+                };
+                view.floor
+                    .find_path(&position, &target, options)
+                    .and_then(|path| path.first().copied())
+                    .map_or(Action::DoNothing, |next| {
+                        Action::Move(Direction::towards(&position, &next))
+                    })
+            }
+            None => Action::DoNothing,
+        };
+    }
+    fn get_next_action(&mut self, _entity: &Entity) -> Option<Action> {
+        Some(mem::take(&mut self.action))
+    }
+}
@@ -0,0 +1,94 @@
+use super::entities::Position;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Evento avvenuto all'interno di un piano durante l'update di giocatori o entità.\
+/// Ogni evento ha il turno in cui è avvenuto, l'eventuale entità che lo ha causato,
+/// l'entità che lo ha subito, il tipo di evento con i relativi valori e la posizione
+/// in cui è avvenuto (None per gli eventi salvati prima che venisse registrata).\
+/// Le entità sono identificate tramite il loro nome.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Event {
+    pub turn: usize,
+    pub source: Option<String>,
+    pub target: String,
+    pub kind: EventKind,
+    #[serde(default)]
+    pub position: Option<Position>,
+}
+
+/// Tutti i tipi di evento che possono avvenire in un piano.\
+/// *Attack* indica il danno inflitto dalla sorgente al bersaglio, mentre *Miss* un attacco mancato.\
+/// *Effect* indica l'effetto che è stato applicato al bersaglio passando su una cella speciale.\
+/// *Damage* indica il danno subito dal bersaglio a causa dell'effetto indicato
+/// (se negativo indica una cura).\
+/// *Death* indica la morte del bersaglio, e la sorgente è chi lo ha ucciso se esiste.\
+/// *LevelUp* indica il nuovo livello raggiunto dal bersaglio.\
+/// *PickUp*, *Drop* e *Use* indicano l'oggetto raccolto, lasciato o usato dal bersaglio.\
+/// *FloorChange* indica il piano in cui è entrato il bersaglio.\
+/// *Victory* indica che il bersaglio ha raggiunto una delle condizioni di vittoria.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EventKind {
+    Attack(i32),
+    Miss,
+    Effect(String),
+    Damage(String, i32),
+    Death,
+    LevelUp(u32),
+    PickUp(String),
+    Drop(String),
+    Use(String),
+    FloorChange(usize),
+    Victory,
+}
+
+impl Event {
+    /// Crea un nuovo evento avvenuto nel turno indicato.
+    pub fn new(turn: usize, source: Option<String>, target: String, kind: EventKind) -> Self {
+        Self {
+            turn,
+            source,
+            target,
+            kind,
+            position: None,
+        }
+    }
+
+    /// Indica la posizione del piano in cui è avvenuto l'evento.
+    pub fn at(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Indica se l'entità con il nome passato è coinvolta nell'evento.
+    pub fn involves(&self, name: &str) -> bool {
+        self.target == name || self.source.as_deref() == Some(name)
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = self.source.as_deref().unwrap_or("something");
+        let target = &self.target;
+        write!(f, "[{:4}] ", self.turn)?;
+        match &self.kind {
+            EventKind::Attack(damage) => write!(f, "{source} hits {target} for {damage} damage"),
+            EventKind::Miss => write!(f, "{source} misses {target}"),
+            EventKind::Effect(effect) => write!(f, "{target} is affected by {effect}"),
+            EventKind::Damage(effect, damage) if *damage < 0 => {
+                write!(f, "{target} heals {} from {effect}", -damage)
+            }
+            EventKind::Damage(effect, damage) => {
+                write!(f, "{target} takes {damage} damage from {effect}")
+            }
+            EventKind::Death if self.source.is_some() => write!(f, "{source} kills {target}"),
+            EventKind::Death => write!(f, "{target} dies"),
+            EventKind::LevelUp(level) => write!(f, "{target} reaches level {level}"),
+            EventKind::PickUp(item) => write!(f, "{target} picks up {item}"),
+            EventKind::Drop(item) => write!(f, "{target} drops {item}"),
+            EventKind::Use(item) => write!(f, "{target} uses {item}"),
+            EventKind::FloorChange(level) => write!(f, "{target} enters floor {level}"),
+            EventKind::Victory => write!(f, "{target} wins the game"),
+        }
+    }
+}
@@ -1,14 +1,68 @@
 use super::{
     cell::Cell,
-    entities::{Entity, Position},
+    entities::{Action, Direction, Entity, EntityUpdate, Position},
+    event::{Event, EventKind},
+    snapshot::FloorSnapshot,
 };
+use rand::RngCore;
 use rand_pcg::Pcg32;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, VecDeque},
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
     fmt::Display,
 };
 
+/// Raggio di visione di base di una entità.\
+/// Indica fino a quante celle di distanza una entità riesce a vedere se non ci sono muri in mezzo.
+pub const SIGHT_RADIUS: usize = 8;
+
+/// Numero massimo di eventi che un piano ricorda.\
+/// Quando vengono aggiunti altri eventi, quelli più vecchi vengono dimenticati.
+pub const EVENT_LOG_SIZE: usize = 100;
+
+/// Moltiplicatori per trasformare le coordinate di un ottante in coordinate del piano.\
+/// Utilizzati dall'algoritmo di shadowcasting per poter calcolare tutti gli 8 ottanti
+/// con lo stesso codice.
+const OCTANTS: [(isize, isize, isize, isize); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
+/// Opzioni utilizzate nella ricerca di un percorso all'interno del piano.\
+/// *avoid_special* indica se il percorso deve evitare le celle con degli effetti.\
+/// *avoid_entities* indica se il percorso deve evitare le celle occupate da altre entità.\
+/// In ogni caso la destinazione è sempre considerata raggiungibile, anche se occupata.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PathOptions {
+    pub avoid_special: bool,
+    pub avoid_entities: bool,
+}
+
+/// Indica le scale prese da un giocatore per lasciare un piano.\
+/// *Up* indica l'entrata, che porta all'uscita del piano precedente.\
+/// *Down* indica l'uscita, che porta all'entrata del piano successivo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stairs {
+    Up,
+    Down,
+}
+
+/// Giocatore che ha lasciato il piano tramite le scale.\
+/// Insieme ad esso vengono portate via anche le entità adiacenti che lo seguono.
+#[derive(Clone, Debug)]
+pub struct Transition {
+    pub player: Entity,
+    pub stairs: Stairs,
+    pub followers: Vec<Entity>,
+}
+
 /// Indica un piano del dungeon, in essa si possono trovare le celle in cui si
 /// cammina e le entità che abitano il piano.\
 /// Per poter accedere a questa struttura è necessario utilizzare FloorPtr e fare get()
@@ -19,6 +73,16 @@ pub struct Floor {
     players: VecDeque<Entity>,
     entities: VecDeque<Entity>,
     rng: Pcg32,
+    #[serde(default)]
+    turn: usize,
+    #[serde(default)]
+    events: VecDeque<Event>,
+    #[serde(skip)]
+    actions: Vec<(String, Action)>,
+    #[serde(skip)]
+    departed: Vec<Entity>,
+    #[serde(skip)]
+    leaving: Vec<Transition>,
 }
 
 impl Floor {
@@ -31,16 +95,152 @@ impl Floor {
             players: VecDeque::new(),
             entities: VecDeque::from(entities),
             grid,
+            turn: 0,
+            events: VecDeque::new(),
+            actions: vec![],
+            departed: vec![],
+            leaving: vec![],
         }
     }
 
-    /// Aggiunge un giocatore al piano e lo inserisce all'entrata.
-    pub fn add_player(&mut self, mut player: Entity) {
-        // todo!() check collision with other entities
-        player.position = self.get_entrance();
+    /// Aggiunge un giocatore al piano e lo inserisce all'entrata.\
+    /// Nel caso in cui l'entrata sia già occupata, il giocatore viene inserito nella
+    /// cella libera più vicina ad essa.\
+    /// Il generatore di numeri casuali del giocatore viene derivato da quello del piano.\
+    /// Inoltre viene aggiornato il piano più profondo raggiunto dal giocatore.\
+    /// Il giocatore sarà l'ultimo a giocare nel prossimo update del piano.
+    pub fn add_player(&mut self, player: Entity) {
+        let entrance = self.get_entrance();
+        self.add_player_at(player, entrance);
+    }
+
+    /// Aggiunge un giocatore al piano nella posizione indicata, o nella cella libera più vicina
+    /// ad essa nel caso sia occupata.\
+    /// Per il resto funziona esattamente come add_player.
+    pub fn add_player_at(&mut self, mut player: Entity, position: Position) {
+        player.position = match self.collisions(&position) {
+            0 => position,
+            _ => self.find_free_cell_near(&position).unwrap_or(position),
+        };
+        player.reseed(self.rng.next_u64());
+        let statistics = player.get_statistics_mut();
+        statistics.deepest_floor = statistics.deepest_floor.max(self.level);
+        player.explore(self);
+        let kind = EventKind::FloorChange(self.level);
+        self.add_event(None, player.get_name().clone(), kind, player.position);
         self.players.push_back(player);
     }
 
+    /// Aggiunge una entità al piano nella cella libera più vicina alla posizione indicata.\
+    /// Una cella è libera se non è un muro, non sono delle scale e non ha entità sopra.\
+    /// Nel caso in cui non ci siano celle libere raggiungibili, l'entità non viene aggiunta.
+    pub fn add_entity_near(&mut self, entity: Entity, position: &Position) {
+        let name = entity.get_name().clone();
+        if let Some(position) = self.place_entity_near(entity, position) {
+            let kind = EventKind::FloorChange(self.level);
+            self.add_event(None, name, kind, position);
+        }
+    }
+
+    /// Aggiunge il boss del piano nella cella libera più vicina all'uscita e i suoi seguaci
+    /// nelle celle libere più vicine a lui.\
+    /// Da questo momento l'uscita rimane chiusa finchè il boss non muore.
+    pub fn add_boss(&mut self, mut boss: Entity, minions: Vec<Entity>) {
+        let exit = self
+            .get_exit()
+            .expect("Exit of the floor should be inside the grid!");
+        boss.boss = true;
+        let position = self.place_entity_near(boss, &exit).unwrap_or(exit);
+        for minion in minions {
+            self.place_entity_near(minion, &position);
+        }
+    }
+
+    /// Restituisce il boss del piano, se esiste ed è ancora vivo.\
+    /// Il boss viene riconosciuto tramite il suo flag e non tramite il nome, in modo che altre
+    /// entità con lo stesso nome non tengano chiusa l'uscita.
+    pub fn get_boss(&self) -> Option<&Entity> {
+        self.entities
+            .iter()
+            .find(|entity| entity.boss && entity.is_alive())
+    }
+
+    /// Indica se l'uscita del piano è chiusa, ovvero se il boss del piano è ancora vivo.
+    pub fn is_exit_locked(&self) -> bool {
+        self.get_boss().is_some()
+    }
+
+    /// Mette l'entità nella cella libera più vicina alla posizione indicata.\
+    /// Viene ritornata la posizione in cui è stata messa, oppure None se non esiste nessuna
+    /// cella libera, e in quel caso l'entità viene scartata.
+    fn place_entity_near(&mut self, mut entity: Entity, position: &Position) -> Option<Position> {
+        let free = self.find_free_cell_near(position)?;
+        entity.position = free;
+        self.entities.push_back(entity);
+        Some(free)
+    }
+
+    /// Cerca la cella libera più vicina alla posizione indicata tramite una visita in ampiezza.\
+    /// La visita attraversa tutte le celle che non sono muri, e la cella libera restituita
+    /// non è un muro, non sono delle scale e non ha nessuna entità sopra.\
+    /// Nel caso in cui non esista, viene ritornato None.
+    pub fn find_free_cell_near(&self, position: &Position) -> Option<Position> {
+        let mut visited = HashSet::from([*position]);
+        let mut queue = VecDeque::from([*position]);
+        while let Some(current) = queue.pop_front() {
+            let cell = self.get_cell(&current);
+            let stairs = matches!(cell, Cell::Entrance | Cell::Exit);
+            if !stairs && !matches!(cell, Cell::Wall) && self.collisions(&current) == 0 {
+                return Some(current);
+            }
+
+            for direction in Direction::all() {
+                let mut next = current;
+                direction.move_from(&mut next);
+                if next.0 < self.grid.len()
+                    && next.1 < self.grid.len()
+                    && !matches!(self.get_cell(&next), Cell::Wall)
+                    && visited.insert(next)
+                {
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Indica il turno corrente del piano, utilizzato per marcare gli eventi.
+    pub fn get_turn(&self) -> usize {
+        self.turn
+    }
+
+    /// Modifica il turno corrente del piano.\
+    /// Viene richiamato dal dungeon prima di ogni update in modo che gli eventi abbiano il turno corretto.
+    pub fn set_turn(&mut self, turn: usize) {
+        self.turn = turn;
+    }
+
+    /// Registra un evento avvenuto nel turno corrente del piano nella posizione indicata.\
+    /// Nel caso in cui il piano abbia già troppi eventi, il più vecchio viene dimenticato.
+    pub fn add_event(
+        &mut self,
+        source: Option<String>,
+        target: String,
+        kind: EventKind,
+        position: Position,
+    ) {
+        if self.events.len() >= EVENT_LOG_SIZE {
+            self.events.pop_front();
+        }
+        let event = Event::new(self.turn, source, target, kind).at(position);
+        self.events.push_back(event);
+    }
+
+    /// Ritorna un iteratore a tutti gli eventi ricordati dal piano, dal più vecchio al più recente.
+    pub fn get_events(&self) -> impl Iterator<Item = &Event> {
+        self.events.iter()
+    }
+
     /// Indica se il piano ha almeno un giocatore in vita o meno
     pub fn has_players(&self) -> bool {
         self.players.iter().any(|player| player.is_alive())
@@ -87,6 +287,20 @@ impl Floor {
         &self.grid[x][y]
     }
 
+    /// Restituisce la posizione dell'uscita del piano.\
+    /// Nel caso in cui il piano non abbia un'uscita viene ritornato None.
+    pub fn get_exit(&self) -> Option<Position> {
+        self.find_cell(|cell| matches!(cell, Cell::Exit))
+    }
+
+    /// Restituisce la posizione della prima cella che soddisfa il predicato passato.
+    fn find_cell(&self, predicate: impl Fn(&Cell) -> bool) -> Option<Position> {
+        self.grid
+            .iter()
+            .enumerate()
+            .find_map(|(x, vec)| vec.iter().position(&predicate).map(|y| Position(x, y)))
+    }
+
     /// Restituisce la posizione dell'entrata del piano.\
     /// Utile come spawn per quando i giocatori arrivano al piano.
     pub fn get_entrance(&self) -> Position {
@@ -125,43 +339,144 @@ impl Floor {
     }
 
     /// Ritorna un eventuale giocatore che si trova sopra la cella di uscita del piano.\
-    /// Nel caso in cui non ci siano giocatori sopra, questo metodo ritornerà None.
+    /// Nel caso in cui non ci siano giocatori sopra, o l'uscita sia chiusa dal boss
+    /// (vedi get_stairs_at), questo metodo ritornerà None.
     pub fn get_player_at_exit(&mut self) -> Option<Entity> {
         let index = self
             .players
             .iter()
-            .enumerate()
-            .filter_map(|(i, player)| {
-                let pos = &player.position;
-                match &self.grid[pos.0][pos.1] {
-                    Cell::Exit => Some(i),
-                    _ => None,
+            .position(|player| self.get_stairs_at(&player.position) == Some(Stairs::Down))?;
+        self.players.remove(index)
+    }
+
+    /// Fa l'update di tutti i giocatori e rimuove quelli non più in vita.\
+    /// I giocatori rimossi possono essere presi tramite take_departed.\
+    /// I giocatori che in questo update si sono spostati sulle scale lasciano il piano,
+    /// e possono essere presi tramite take_leaving.\
+    /// Prima di fare l'update, ogni giocatore memorizza le celle che riesce a vedere.\
+    /// Le azioni decise dai giocatori vengono salvate e possono essere prese tramite take_actions.\
+    /// Alla fine dell'update l'ordine dei giocatori viene ruotato, in modo che ad ogni update
+    /// sia un giocatore diverso a giocare per primo.
+    pub fn update_players(&mut self) {
+        for _ in 0..self.players.len() {
+            let mut player = self.players.pop_front().unwrap();
+            let previous = player.position;
+            let name = player.get_name().clone();
+            player.explore(self);
+
+            let (player, actions) = player.update_with_actions(self);
+            for action in actions {
+                self.actions.push((name.clone(), action));
+            }
+            match player {
+                EntityUpdate::Alive(mut player) => {
+                    if self.collisions(&player.position) > 0 {
+                        player.position = previous;
+                    }
+                    let stairs = self.get_stairs_at(&player.position);
+                    match stairs.filter(|_| player.position != previous) {
+                        Some(stairs) => {
+                            let followers = self.take_followers(&player.position);
+                            self.leaving.push(Transition {
+                                player,
+                                stairs,
+                                followers,
+                            });
+                        }
+                        None => self.players.push_back(player),
+                    }
                 }
-            })
-            .next();
+                EntityUpdate::Dead(player) | EntityUpdate::Removed(player) => {
+                    self.departed.push(player)
+                }
+            }
+        }
 
-        if let Some(i) = index {
-            self.players.remove(i)
-        } else {
-            None
+        if !self.players.is_empty() {
+            self.players.rotate_left(1);
         }
     }
 
-    /// Fa l'update di tutti i giocatori e rimuove quelli non più in vita
-    pub fn update_players(&mut self) {
+    /// Rimuove dal piano tutti i giocatori che non sono più in vita, ad esempio perchè uccisi
+    /// dalle entità durante il loro update.\
+    /// In questo modo i giocatori escono dal gioco nello stesso turno in cui muoiono, senza
+    /// aspettare il prossimo update; possono poi essere presi tramite take_departed.
+    pub fn remove_dead_players(&mut self) {
         for _ in 0..self.players.len() {
             let player = self.players.pop_front().unwrap();
-            let previous = player.position;
+            if player.is_alive() {
+                self.players.push_back(player);
+                continue;
+            }
 
-            if let Some(mut player) = player.update(self) {
-                if self.collisions(&player.position) > 0 {
-                    player.position = previous;
+            match player.update_with_actions(self).0 {
+                EntityUpdate::Alive(player) => self.players.push_back(player),
+                EntityUpdate::Dead(player) | EntityUpdate::Removed(player) => {
+                    self.departed.push(player)
                 }
-                self.players.push_back(player);
             }
         }
     }
 
+    /// Rimuove dal piano tutti i giocatori vivi per cui la condizione passata è vera,
+    /// notificando loro la vittoria.\
+    /// I giocatori rimossi vengono restituiti nell'ordine in cui si trovavano nel piano.
+    pub fn take_winners(&mut self, condition: impl Fn(&Entity) -> bool) -> Vec<Entity> {
+        let (mut winners, others): (Vec<_>, Vec<_>) = std::mem::take(&mut self.players)
+            .into_iter()
+            .partition(|player| player.is_alive() && condition(player));
+        self.players = others.into();
+
+        for winner in winners.iter_mut() {
+            let name = winner.get_name().clone();
+            self.add_event(None, name, EventKind::Victory, winner.position);
+            winner.win(self);
+        }
+        winners
+    }
+
+    /// Restituisce tutti i giocatori che hanno lasciato il piano tramite le scale,
+    /// insieme alle entità che li hanno seguiti.\
+    /// Come per take_departed, una successiva chiamata restituirà solamente i nuovi giocatori.
+    pub fn take_leaving(&mut self) -> Vec<Transition> {
+        std::mem::take(&mut self.leaving)
+    }
+
+    /// Indica le scale che si trovano nella posizione indicata.\
+    /// L'entrata del piano 0 non porta da nessuna parte e quindi non viene considerata.
+    pub fn get_stairs_at(&self, position: &Position) -> Option<Stairs> {
+        match self.get_cell(position) {
+            Cell::Exit if !self.is_exit_locked() => Some(Stairs::Down),
+            Cell::Entrance if self.level > 0 => Some(Stairs::Up),
+            _ => None,
+        }
+    }
+
+    /// Rimuove dal piano tutte le entità vive adiacenti alla posizione che seguono le scale.
+    fn take_followers(&mut self, position: &Position) -> Vec<Entity> {
+        let (followers, others): (Vec<_>, Vec<_>) = std::mem::take(&mut self.entities)
+            .into_iter()
+            .partition(|entity: &Entity| {
+                entity.follow_stairs && entity.is_alive() && entity.position.distance(position) == 1
+            });
+        self.entities = others.into();
+        followers
+    }
+
+    /// Restituisce tutti i giocatori che sono usciti dal gioco perchè morti o rimossi.\
+    /// I giocatori vengono rimossi dal piano, quindi una successiva chiamata restituirà
+    /// solamente i giocatori usciti dopo questa chiamata.
+    pub fn take_departed(&mut self) -> Vec<Entity> {
+        std::mem::take(&mut self.departed)
+    }
+
+    /// Restituisce tutte le azioni decise dai giocatori nei vari update, insieme al loro nome.\
+    /// Le azioni vengono rimosse dal piano, quindi una successiva chiamata restituirà
+    /// solamente le azioni decise dopo questa chiamata.
+    pub fn take_actions(&mut self) -> Vec<(String, Action)> {
+        std::mem::take(&mut self.actions)
+    }
+
     /// Fa l'update di tutte le entità e rimuove eventualmente quelle non più in vita
     pub fn update_entities(&mut self) {
         for _ in 0..self.entities.len() {
@@ -182,6 +497,18 @@ impl Floor {
         FloorView::new(self, entity)
     }
 
+    /// Ritorna un iteratore a tutti i giocatori del piano.
+    pub fn get_players(&self) -> impl Iterator<Item = &Entity> {
+        self.players.iter()
+    }
+
+    /// Restituisce il giocatore del piano con il nome indicato in modo da poterlo modificare.
+    pub fn get_player_mut(&mut self, name: &str) -> Option<&mut Entity> {
+        self.players
+            .iter_mut()
+            .find(|player| player.get_name() == name)
+    }
+
     /// Ritorna un iteratore a tutte le entità del piano.\
     /// Le entità del piano si dividono in giocatori e entità, e questo iteratore le ritorna tutte,
     /// passando prima dai giocatori e poi da tutto il resto.
@@ -189,6 +516,164 @@ impl Floor {
         self.players.iter().chain(self.entities.iter())
     }
 
+    /// Cerca il percorso più breve tra le due posizioni indicate tramite l'algoritmo A*.\
+    /// Il percorso ritornato non contiene la posizione di partenza ma contiene quella di arrivo.\
+    /// I muri non sono mai attraversabili, mentre le celle speciali e quelle occupate
+    /// da altre entità vengono evitate in base alle opzioni passate.\
+    /// Nel caso in cui la destinazione non sia raggiungibile, viene ritornato None.\
+    /// https://en.wikipedia.org/wiki/A*_search_algorithm
+    pub fn find_path(
+        &self,
+        from: &Position,
+        to: &Position,
+        options: PathOptions,
+    ) -> Option<Vec<Position>> {
+        let occupied = if options.avoid_entities {
+            self.get_all_entities()
+                .map(|entity| entity.position)
+                .collect::<HashSet<_>>()
+        } else {
+            HashSet::new()
+        };
+        let walkable = |pos: &Position| {
+            pos == to
+                || match self.get_cell(pos) {
+                    Cell::Wall => false,
+                    Cell::Special(_) => !options.avoid_special && !occupied.contains(pos),
+                    _ => !occupied.contains(pos),
+                }
+        };
+
+        let mut came_from = HashMap::new();
+        let mut costs = HashMap::from([(*from, 0)]);
+        let mut queue = BinaryHeap::from([Reverse((from.distance(to), *from))]);
+
+        while let Some(Reverse((_, current))) = queue.pop() {
+            if current == *to {
+                let mut path = vec![current];
+                while let Some(previous) = came_from.get(path.last().unwrap()) {
+                    path.push(*previous);
+                }
+                path.pop(); // starting position
+                path.reverse();
+                return Some(path);
+            }
+
+            let cost = costs[&current] + 1;
+            for direction in Direction::all() {
+                let mut next = current;
+                direction.move_from(&mut next);
+                if next == current || next.0 >= self.get_size() || next.1 >= self.get_size() {
+                    continue;
+                }
+                if walkable(&next) && costs.get(&next).is_none_or(|old| cost < *old) {
+                    costs.insert(next, cost);
+                    came_from.insert(next, current);
+                    queue.push(Reverse((cost + next.distance(to), next)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Calcola tutte le posizioni visibili a partire dall'origine indicata entro il raggio passato.\
+    /// I muri bloccano la visuale, ma sono comunque visibili anch'essi.\
+    /// L'algoritmo utilizzato è il recursive shadowcasting che si può trovare al link:\
+    /// https://www.roguebasin.com/index.php/FOV_using_recursive_shadowcasting
+    pub fn get_visible_from(&self, origin: &Position, radius: usize) -> HashSet<Position> {
+        let mut visible = HashSet::new();
+        visible.insert(*origin);
+        for octant in OCTANTS {
+            self.cast_light(&mut visible, origin, radius, 1, 1.0, 0.0, octant);
+        }
+        visible
+    }
+
+    /// Indica se la cella nella posizione passata blocca la visuale.\
+    /// Le posizioni fuori dal piano sono considerate come muri.
+    pub fn blocks_sight(&self, x: isize, y: isize) -> bool {
+        let size = self.grid.len() as isize;
+        if x < 0 || y < 0 || x >= size || y >= size {
+            return true;
+        }
+        matches!(self.grid[x as usize][y as usize], Cell::Wall)
+    }
+
+    /// Calcola ricorsivamente le celle illuminate di un ottante.\
+    /// Le slope start ed end indicano la porzione di ottante non ancora coperta da un muro,
+    /// mentre row indica la distanza dall'origine da cui iniziare.
+    #[allow(clippy::too_many_arguments)]
+    fn cast_light(
+        &self,
+        visible: &mut HashSet<Position>,
+        origin: &Position,
+        radius: usize,
+        row: usize,
+        mut start: f32,
+        end: f32,
+        (xx, xy, yx, yy): (isize, isize, isize, isize),
+    ) {
+        if start < end {
+            return;
+        }
+
+        let radius_squared = (radius * radius) as isize;
+        for distance in row..=radius {
+            let dy = -(distance as isize);
+            let mut dx = dy - 1;
+            let mut blocked = false;
+            let mut new_start = start;
+
+            while dx < 0 {
+                dx += 1;
+                let x = origin.0 as isize + dx * xx + dy * xy;
+                let y = origin.1 as isize + dx * yx + dy * yy;
+                let l_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let r_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+
+                if start < r_slope {
+                    continue;
+                } else if end > l_slope {
+                    break;
+                }
+
+                let opaque = self.blocks_sight(x, y);
+                if dx * dx + dy * dy <= radius_squared && x >= 0 && y >= 0 {
+                    let pos = Position(x as usize, y as usize);
+                    if pos.0 < self.grid.len() && pos.1 < self.grid.len() {
+                        visible.insert(pos);
+                    }
+                }
+
+                if blocked {
+                    if opaque {
+                        new_start = r_slope;
+                    } else {
+                        blocked = false;
+                        start = new_start;
+                    }
+                } else if opaque && distance < radius {
+                    blocked = true;
+                    let octant = (xx, xy, yx, yy);
+                    self.cast_light(
+                        visible,
+                        origin,
+                        radius,
+                        distance + 1,
+                        start,
+                        l_slope,
+                        octant,
+                    );
+                    new_start = r_slope;
+                }
+            }
+
+            if blocked {
+                break;
+            }
+        }
+    }
+
     /// Controlla che nella posizione indicata non ci siano altre entità e restituisce il numero di collisioni trovate.\
     /// Questo metodo controlla TUTTE le entità e i giocatori del piano, quindi si svolge in O(n)
     fn collisions(&self, pos: &Position) -> usize {
@@ -201,19 +686,25 @@ impl Floor {
 /// Struttura di mezzo tra un piano e il gioco vero e proprio.\
 /// Utilizzata per la comunicazione con le entità per poter aggiornare quello che vedono.\
 /// Infatti internamente ha solo alcuni pezzi del gioco per non far mostrare tutto.\
+/// Le celle che l'entità può vedere sono calcolate alla creazione della vista tramite la linea di vista.
 pub struct FloorView<'a> {
     pub entity: &'a Entity,
     pub floor: &'a Floor,
+    visible: HashSet<Position>,
 }
 
 /// Struttura di mezzo usata per far visualizzare una cella e
 /// l'eventuale entità che si trova sopra.\
 /// Questa truttura viene usata solamente per prendere i valori in modo read-only
-/// e viene utilizzata da FloorView nel metodo get_grid.
+/// e viene utilizzata da FloorView nel metodo get_grid.\
+/// Nel caso in cui la cella non sia mai stata vista dall'entità, essa sarà None.
+/// Se invece la cella è stata esplorata ma non è visibile in questo momento, allora
+/// visible sarà false e non verrà mostrata nessuna entità sopra di essa.
 pub struct CellView<'a> {
     pub position: Position,
     pub entity: Option<&'a Entity>,
-    pub cell: &'a Cell,
+    pub cell: Option<&'a Cell>,
+    pub visible: bool,
 }
 
 impl<'a> FloorView<'a> {
@@ -222,46 +713,92 @@ impl<'a> FloorView<'a> {
     /// in questo momento sul piano dell'entità passata in input.
     pub fn new(floor: &'a Floor, entity: &'a Entity) -> Self {
         Self {
-            entity: &entity,
-            floor: &floor,
+            visible: floor.get_visible_from(&entity.position, entity.get_sight_radius()),
+            entity,
+            floor,
         }
     }
 
-    /// Ritorna un iteratore contenente gli iteratori di ogni riga del piano.
-    pub fn get_grid(&self, view: usize) -> impl Iterator<Item = impl Iterator<Item = CellView>> {
+    /// Indica se la posizione passata è visibile dall'entità della vista.
+    pub fn is_visible(&self, position: &Position) -> bool {
+        self.visible.contains(position)
+    }
+
+    /// Ritorna un iteratore agli eventi avvenuti nel piano, dal più vecchio al più recente.\
+    /// Il turno indicato permette di filtrare gli eventi avvenuti prima di esso.\
+    /// Vengono restituiti solamente gli eventi che coinvolgono l'entità della vista o che
+    /// sono avvenuti in una posizione che essa può vedere, in modo da non rivelare quello
+    /// che succede fuori dalla sua linea di vista.
+    pub fn get_events(&self, since: usize) -> impl Iterator<Item = &Event> {
+        let name = self.entity.get_name();
+        self.floor.get_events().filter(move |event| {
+            event.turn >= since
+                && (event.involves(name) || event.position.is_some_and(|pos| self.is_visible(&pos)))
+        })
+    }
+
+    /// Crea una fotografia della vista che non dipende dal piano o dall'entità,
+    /// considerando solamente le celle entro il raggio indicato.
+    pub fn snapshot(&self, radius: usize) -> FloorSnapshot {
+        FloorSnapshot::new(self, radius)
+    }
+
+    /// Ritorna un iteratore contenente gli iteratori di ogni riga del piano.\
+    /// Vengono mostrate solamente le celle visibili o già esplorate dall'entità.
+    pub fn get_grid(
+        &self,
+        view: usize,
+    ) -> impl Iterator<Item = impl Iterator<Item = CellView<'_>>> {
+        self.get_viewport(2 * view, 2 * view)
+    }
+
+    /// Come get_grid, ma la porzione di piano restituita è un rettangolo largo *width* celle
+    /// e alto *height* righe, centrato sull'entità quando non si trova vicino ai bordi del piano.\
+    /// Le righe sono restituite dall'alto verso il basso.
+    pub fn get_viewport(
+        &self,
+        width: usize,
+        height: usize,
+    ) -> impl Iterator<Item = impl Iterator<Item = CellView<'_>>> {
         let grid = &self.floor.grid;
+        let level = self.floor.level;
         let entities = self
             .floor
             .get_all_entities()
             .chain(std::iter::once(self.entity))
+            .filter(|entity| self.is_visible(&entity.position))
             .map(|entity| (&entity.position, entity))
             .collect::<HashMap<_, _>>();
         let entities = std::rc::Rc::new(std::cell::RefCell::new(entities));
 
-        let temp_x = self.entity.position.0.saturating_sub(view);
-        let temp_y = self.entity.position.1.saturating_sub(view);
-        let size_x = temp_x.saturating_add(2 * view).min(grid.len());
-        let size_y = temp_y.saturating_add(2 * view).min(grid.len());
-        let view_x = size_x.saturating_sub(2 * view);
-        let view_y = size_y.saturating_sub(2 * view);
+        let temp_x = self.entity.position.0.saturating_sub(width / 2);
+        let temp_y = self.entity.position.1.saturating_sub(height / 2);
+        let size_x = temp_x.saturating_add(width).min(grid.len());
+        let size_y = temp_y.saturating_add(height).min(grid.len());
+        let view_x = size_x.saturating_sub(width);
+        let view_y = size_y.saturating_sub(height);
 
         (view_y..size_y).rev().map(move |y| {
             let entities = entities.clone();
             (view_x..size_x)
                 .map(move |x| Position(x, y))
                 .map(move |position| {
-                    let cell = &grid[position.0][position.1];
+                    let visible = self.is_visible(&position);
+                    let explored = visible || self.entity.has_explored(level, &position);
+                    let cell = explored.then_some(&grid[position.0][position.1]);
                     let entity = entities.borrow_mut().remove(&position);
                     CellView {
                         position,
                         entity,
                         cell,
+                        visible,
                     }
                 })
         })
     }
 
-    /// Rappresentazione del piano come matrice di char
+    /// Rappresentazione del piano come matrice di char.\
+    /// Le celle mai viste vengono rappresentate come spazi vuoti.
     pub fn as_char_grid(&self) -> Vec<Vec<char>> {
         self.get_grid(self.floor.grid.len())
             .map(|iter| {
@@ -270,10 +807,10 @@ impl<'a> FloorView<'a> {
                         return [' ', e.direction.as_char(), ' '];
                     }
 
-                    let ch = view.cell.as_char();
                     match view.cell {
-                        Cell::Wall => [ch, ch, ch],
-                        _ => [' ', ch, ' '],
+                        Some(Cell::Wall) => [Cell::Wall.as_char(); 3],
+                        Some(cell) => [' ', cell.as_char(), ' '],
+                        None => [' '; 3],
                     }
                 })
                 .chain(std::iter::once('\n'))
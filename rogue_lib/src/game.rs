@@ -1,15 +1,20 @@
 use super::{
     config::Config,
-    entities::{Behavior, Entity},
-    floor::Floor,
+    entities::{Behavior, Entity, Position},
+    floor::{Floor, Stairs, Transition},
     generator::Generator,
+    replay::Replay,
+    save::{self, SaveError, SaveFormat},
+    score::Score,
 };
 use rand::{RngCore, SeedableRng};
 use rand_pcg::Pcg32;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     fs::File,
-    io::{self, BufReader, BufWriter},
+    hash::Hasher,
+    io::{BufReader, BufWriter},
 };
 
 /// Rappresenta un Dungeon in stile RogueLike.\
@@ -20,6 +25,29 @@ pub struct Dungeon {
     floors: Vec<Floor>,
     config: Config,
     rng: Pcg32,
+    #[serde(default)]
+    turn: usize,
+    #[serde(default)]
+    departed: Vec<Entity>,
+    #[serde(default)]
+    outcomes: BTreeMap<String, GameOutcome>,
+    #[serde(default)]
+    scores: Vec<Score>,
+    #[serde(skip)]
+    replay: Option<Replay>,
+    #[serde(skip)]
+    floor_changes: Vec<String>,
+}
+
+/// Risultato della partita di un giocatore.\
+/// *Victory* indica che il giocatore ha raggiunto una delle condizioni di vittoria della configurazione.\
+/// *Defeat* indica che il giocatore è morto.\
+/// *Quit* indica che il giocatore è uscito dal gioco perchè non ha più indicato un'azione.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum GameOutcome {
+    Victory,
+    Defeat,
+    Quit,
 }
 
 impl Dungeon {
@@ -34,36 +62,57 @@ impl Dungeon {
             rng: Pcg32::seed_from_u64(config.game_seed),
             floors: vec![],
             config,
+            turn: 0,
+            departed: vec![],
+            outcomes: BTreeMap::new(),
+            scores: vec![],
+            replay: None,
+            floor_changes: vec![],
         };
         game.build_next_floor();
         game
     }
 
     /// Carica il dungeon da un file.\
-    /// Il file deve essere formattato tramite json, altrimenti viene ritornato un errore.
-    pub fn load(filename: &str) -> io::Result<Self> {
+    /// Il file deve essere stato creato tramite save, in qualsiasi formato dato che viene
+    /// riconosciuto automaticamente, anche da una versione precedente del gioco
+    /// dato che i salvataggi vecchi vengono migrati al formato attuale (vedi save::read).\
+    /// In caso contrario viene ritornato un errore che indica il tipo di problema.
+    pub fn load(filename: &str) -> Result<Self, SaveError> {
         let file = File::open(filename)?;
         let reader = BufReader::new(file);
-        let dungeon: Self = serde_json::from_reader(reader)?;
-        Ok(dungeon)
+        save::read(reader)
     }
 
     /// Salva il dungeon corrente nel file indicato.\
-    /// Il salvataggio viene fatto tramite serializzazione JSON in modo che sia facile da vedere.\
+    /// Il salvataggio viene fatto nel formato scelto: JSON in modo che sia facile da vedere,
+    /// oppure binario in modo che occupi meno spazio; in entrambi i casi insieme ad una
+    /// intestazione che indica la versione del formato (vedi save::write).\
     /// Nel caso in cui ci siano problemi con I/O, viene ritornato un errore.
-    pub fn save(&mut self, filename: &str) -> io::Result<()> {
+    pub fn save(&mut self, filename: &str, format: SaveFormat) -> Result<(), SaveError> {
         let file = File::create(filename)?;
         let writer = BufWriter::new(file);
-        let _ = serde_json::to_writer_pretty(writer, self)?;
-        Ok(())
+        save::write(self, format, writer)
     }
 
     /// Aggiunge un giocatore al Dungeon, esso avrà le statistiche di base assegnate
     /// ad esso tramite la configurazione indicata nel costruttore.\
-    /// Il giocatore appena inserito si troverà al piano 0.
+    /// Il giocatore appena inserito si troverà al piano 0, all'entrata o nella cella libera
+    /// più vicina ad essa nel caso in cui sia già occupata da un altro giocatore.\
+    /// I giocatori sono identificati dal loro nome (ad esempio nei risultati della partita),
+    /// quindi ognuno deve averne uno diverso, anche rispetto a quelli già usciti dal gioco.
     pub fn add_player(&mut self, name: String, decider: Box<dyn Behavior>) {
+        assert!(
+            self.get_player(&name).is_none(),
+            "Player names must be unique!"
+        );
+        if let Some(replay) = &mut self.replay {
+            replay.add_player(name.clone());
+        }
+
         let stats = &self.config.player_stats;
-        let player = Entity::new(name, stats.health, stats.attack, decider);
+        let growth = stats.growth.clone();
+        let player = Entity::new_with(name, stats.health, stats.get_stats(), growth, decider);
         self.floors[0].add_player(player);
     }
 
@@ -82,32 +131,229 @@ impl Dungeon {
         &self.floors[index]
     }
 
+    /// Ritorna un iteratore a tutti i giocatori ancora in gioco in ogni piano del dungeon.
+    pub fn get_players(&self) -> impl Iterator<Item = &Entity> {
+        self.floors.iter().flat_map(|floor| floor.get_players())
+    }
+
+    /// Restituisce il giocatore con il nome indicato, sia che sia ancora in gioco o che ne sia uscito.
+    pub fn get_player(&self, name: &str) -> Option<&Entity> {
+        self.get_players()
+            .chain(self.get_departed())
+            .find(|player| player.get_name() == name)
+    }
+
+    /// Restituisce il giocatore ancora in gioco con il nome indicato in modo da poterlo modificare.
+    pub fn get_player_mut(&mut self, name: &str) -> Option<&mut Entity> {
+        self.floors
+            .iter_mut()
+            .find_map(|floor| floor.get_player_mut(name))
+    }
+
+    /// Restituisce il livello del piano in cui si trova il giocatore indicato.\
+    /// Nel caso in cui il giocatore non sia più in gioco, viene restituito None.
+    pub fn get_player_level(&self, name: &str) -> Option<usize> {
+        self.floors
+            .iter()
+            .find(|floor| floor.get_players().any(|player| player.get_name() == name))
+            .map(Floor::get_level)
+    }
+
+    /// Indica se il giocatore con il nome indicato è ancora in gioco.\
+    /// Un giocatore esce dal gioco quando muore o quando non riesce più a decidere un'azione,
+    /// e da quel momento la partita continua solamente per gli altri giocatori.
+    pub fn is_in_game(&self, name: &str) -> bool {
+        self.get_players().any(|player| player.get_name() == name)
+    }
+
+    /// Ritorna un iteratore a tutti i giocatori che sono usciti dal gioco perchè morti o rimossi.\
+    /// I giocatori mantengono lo stato in cui si trovavano al momento dell'uscita,
+    /// in modo da poter vedere, ad esempio, le statistiche raccolte.
+    pub fn get_departed(&self) -> impl Iterator<Item = &Entity> {
+        self.departed.iter()
+    }
+
+    /// Restituisce il risultato della partita del giocatore indicato.\
+    /// Nel caso in cui il giocatore sia ancora in gioco o non esista, viene restituito None.
+    pub fn get_outcome(&self, name: &str) -> Option<GameOutcome> {
+        self.outcomes.get(name).copied()
+    }
+
+    /// Ritorna un iteratore a tutti i risultati dei giocatori usciti dal gioco, ordinati per nome.
+    pub fn get_outcomes(&self) -> impl Iterator<Item = (&String, &GameOutcome)> {
+        self.outcomes.iter()
+    }
+
+    /// Ritorna un iteratore ai punteggi dei giocatori che hanno finito la partita, vincendo
+    /// o morendo, nell'ordine in cui l'hanno finita.\
+    /// I giocatori usciti dal gioco senza finire la partita non hanno un punteggio.
+    pub fn get_scores(&self) -> impl Iterator<Item = &Score> {
+        self.scores.iter()
+    }
+
+    /// Ritorna un iteratore ai nomi dei giocatori che hanno cambiato piano tramite le scale
+    /// durante l'ultimo turno calcolato, nell'ordine in cui le hanno prese.\
+    /// Non viene salvato insieme al dungeon, quindi dopo il caricamento è vuoto.
+    pub fn get_floor_changes(&self) -> impl Iterator<Item = &String> {
+        self.floor_changes.iter()
+    }
+
+    /// Restituisce il numero di turni calcolati fino ad ora.
+    pub fn get_turn(&self) -> usize {
+        self.turn
+    }
+
+    /// Restituisce la configurazione con cui è stato creato il dungeon.
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Inizia la registrazione di tutte le azioni fatte dai giocatori.\
+    /// La registrazione deve iniziare prima di calcolare qualunque turno e prima di
+    /// aggiungere i giocatori, in modo che la partita possa essere riprodotta dall'inizio.
+    pub fn start_recording(&mut self) {
+        assert!(
+            self.turn == 0,
+            "Recording must start before the first turn!"
+        );
+        assert!(
+            self.get_players().next().is_none() && self.departed.is_empty(),
+            "Recording must start before adding any player!"
+        );
+        self.replay = Some(Replay::new(self.config.clone()));
+    }
+
+    /// Termina la registrazione e restituisce il replay della partita.\
+    /// Nel caso in cui la registrazione non sia mai iniziata, viene restituito None.
+    pub fn stop_recording(&mut self) -> Option<Replay> {
+        self.replay.take()
+    }
+
+    /// Calcola un valore hash dello stato attuale del dungeon.\
+    /// Nel calcolo vengono considerate tutte le celle e tutte le entità di ogni piano con le loro
+    /// posizioni e vita, in modo da poter controllare velocemente se due partite sono nello stesso stato.\
+    /// Il valore viene calcolato con StableHasher, quindi rimane lo stesso anche cambiando
+    /// versione del compilatore o piattaforma e può essere salvato nei replay.
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        hasher.write_usize(self.turn);
+        for floor in &self.floors {
+            hasher.write_usize(floor.get_level());
+            for x in 0..floor.get_size() {
+                for y in 0..floor.get_size() {
+                    hasher.write_u32(floor.get_cell(&Position(x, y)).as_char() as u32);
+                }
+            }
+            for entity in floor.get_all_entities() {
+                hasher.write(entity.get_name().as_bytes());
+                hasher.write_u8(0xff);
+                hasher.write_usize(entity.position.0);
+                hasher.write_usize(entity.position.1);
+                hasher.write_u32(entity.direction.as_char() as u32);
+                hasher.write_i32(entity.get_health());
+            }
+        }
+        hasher.finish()
+    }
+
     /// Funzione principale del dungeon.\
     /// In essa viene fatto fare l'update ai giocatori e ad ogni piano.
     /// In generale l'algoritmo è il seguente per ogni piano in cui si trova un giocatore:\
-    /// - I giocatori fanno le loro mosse.\
+    /// - I giocatori fanno le loro mosse, ognuno una volta sola per turno, e ad ogni turno
+    ///   l'ordine dei giocatori del piano viene ruotato in modo che nessuno sia sempre il primo.\
     /// - Se un giocatore non è più in vita o non può indicare l'azione da fare, viene rimosso
-    /// - Update di tutte le entità del piano
-    /// - Modifica di piano di eventuali giocatori
+    ///   e salvato tra i giocatori usciti dal gioco
+    /// - Update di tutte le entità del piano e rimozione dei giocatori uccisi da esse
+    ///
+    /// Solo dopo aver aggiornato tutti i piani vengono spostati i giocatori che hanno preso
+    /// le scale, insieme alle entità che li seguono, in modo che nessuno giochi due volte nello
+    /// stesso turno. I giocatori arrivano nel nuovo piano nell'ordine in cui hanno preso le scale
+    /// (vedi get_floor_changes).\
+    /// Dopodichè i giocatori che hanno raggiunto una condizione di vittoria escono dal gioco,
+    /// e viene salvato il risultato di tutti quelli usciti in questo turno insieme al punteggio
+    /// di quelli che hanno vinto o sono morti.\
+    /// Alla fine vengono registrate le azioni dei giocatori nel caso la registrazione sia attiva.
     pub fn compute_turn(&mut self) {
-        let moved = self.floors.iter_mut().fold(None, |moved, floor| {
+        self.floor_changes.clear();
+        let mut leaving = vec![];
+        for floor in self.floors.iter_mut() {
+            floor.set_turn(self.turn);
             if floor.has_players() {
-                let _ = floor.update_players();
+                floor.update_players();
                 floor.update_entities();
+                floor.remove_dead_players();
             }
 
-            if let Some(player) = moved {
-                floor.add_player(player);
-            }
+            let level = floor.get_level();
+            leaving.extend(floor.take_leaving().into_iter().map(|t| (level, t)));
+        }
+
+        for (level, transition) in leaving {
+            let name = transition.player.get_name().clone();
+            self.floor_changes.push(name);
+            self.move_player(level, transition);
+        }
+
+        let victory = &self.config.victory;
+        for floor in self.floors.iter_mut() {
+            let winners = floor.take_winners(|player| victory.is_reached(player.get_statistics()));
+            let departed = floor.take_departed().into_iter().map(|player| {
+                let outcome = match player.is_alive() {
+                    true => GameOutcome::Quit,
+                    false => GameOutcome::Defeat,
+                };
+                (player, outcome)
+            });
+            let winners = winners
+                .into_iter()
+                .map(|player| (player, GameOutcome::Victory));
 
-            floor.get_player_at_exit()
-        });
+            for (player, outcome) in winners.chain(departed) {
+                if outcome != GameOutcome::Quit {
+                    self.scores.push(Score::new(&player, outcome));
+                }
+                self.outcomes.insert(player.get_name().clone(), outcome);
+                self.departed.push(player);
+            }
+        }
+        self.turn += 1;
+        let actions = self
+            .floors
+            .iter_mut()
+            .flat_map(|floor| floor.take_actions())
+            .collect();
+        if self.replay.is_some() {
+            let checksum = self.checksum();
+            if let Some(replay) = &mut self.replay {
+                replay.add_turn(actions, checksum);
+            }
+        }
+    }
 
-        if let Some(player) = moved {
+    /// Sposta il giocatore che ha lasciato il piano indicato in quello raggiunto tramite le scale.\
+    /// Salendo il giocatore arriva all'uscita del piano precedente, mentre scendendo arriva
+    /// all'entrata del piano successivo, che viene generato nel caso non esista ancora.\
+    /// Le entità che lo seguono vengono messe nelle celle libere più vicine a lui.
+    fn move_player(&mut self, level: usize, transition: Transition) {
+        let level = match transition.stairs {
+            Stairs::Up => level - 1,
+            Stairs::Down => level + 1,
+        };
+        while self.floors.len() <= level {
             self.build_next_floor();
-            let len = self.floors.len();
-            let floor = &mut self.floors[len - 1];
-            floor.add_player(player);
+        }
+
+        let floor = &mut self.floors[level];
+        let position = match transition.stairs {
+            Stairs::Up => floor
+                .get_exit()
+                .expect("Exit of the floor should be inside the grid!"),
+            Stairs::Down => floor.get_entrance(),
+        };
+        floor.set_turn(self.turn);
+        floor.add_player_at(transition.player, position);
+        for follower in transition.followers {
+            floor.add_entity_near(follower, &position);
         }
     }
 
@@ -120,3 +366,64 @@ impl Dungeon {
         self.floors.push(floor);
     }
 }
+
+/// Hasher FNV-1a a 64 bit.\
+/// A differenza di DefaultHasher, il suo algoritmo è fissato e gli interi vengono sempre scritti
+/// in little endian a 64 bit, quindi i valori calcolati rimangono gli stessi tra versioni diverse
+/// del compilatore e tra piattaforme diverse.\
+/// Viene usato per tutti i valori hash che vengono salvati su file, come i checksum dei replay.\
+/// https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function
+#[derive(Clone, Debug)]
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    /// Crea un nuovo hasher senza aver ancora scritto nessun valore.
+    pub fn new() -> Self {
+        Self(Self::OFFSET)
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(i as u64);
+    }
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i as u64);
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+    fn write_i16(&mut self, i: i16) {
+        self.write_i64(i as i64);
+    }
+    fn write_i32(&mut self, i: i32) {
+        self.write_i64(i as i64);
+    }
+    fn write_i64(&mut self, i: i64) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
+}
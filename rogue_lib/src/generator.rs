@@ -1,21 +1,28 @@
 use super::{
     cell::Cell,
-    config::Config,
+    config::{Config, ConfigEntity, ConfigMaze, ConfigPrefab},
     entities::{
         Direction::{self, Down, Left, Right, Up},
         Position,
     },
     floor::Floor,
+    prefab::{Prefab, PrefabTile},
 };
 use crate::entities::Entity;
+use dyn_clone::{clone_trait_object, DynClone};
 use rand::{Rng, SeedableRng};
 use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     fmt::Display,
     ops::Range,
 };
 
+/// Numero massimo di volte in cui viene rigenerata la struttura di un piano nel caso in cui
+/// non rispetti i vincoli della configurazione.
+pub const MAX_GENERATION_ATTEMPTS: usize = 100;
+
 /// Generatore del gioco che può creare dei piani del dungeon.
 /// Idealmente questo generatore si comporta come il pattern Factory.
 /// Per far si che funzioni ha bisongo di un seed per la generazione del piano
@@ -49,54 +56,66 @@ impl<'a> Generator<'a> {
             config,
         }
     }
-    /// Crea un nuovo labirinto a partire dalle configurazioni passate in input.\
-    /// Questo metodo creerà un piano avente delle stanze collegate tra di loro tramite dei
-    /// corridoi; inoltre in esse verranno inseriti degli effetti.
+    /// Crea un nuovo piano a partire dalle configurazioni passate in input.\
+    /// La struttura del piano (muri, entrata e uscita) viene creata dal generatore indicato
+    /// nella configurazione e controllata tramite generate_valid_layout; dopodichè in esso
+    /// verranno inseriti effetti, oggetti ed entità.
     pub fn build_floor(mut self) -> Floor {
-        let maze_gen = &self.config.maze_generation;
-        let room_size = self.config.maze_generation.room_size.clone();
-        let mut gen = MazeGenerator::new(self.size, room_size, &mut self.rng);
-        let mut grid = gen
-            .generate_rooms(maze_gen.room_placing_attempts)
-            .generate_labyrinth(maze_gen.straight_percentage)
-            .connect_regions()
-            .remove_dead_ends(maze_gen.dead_ends)
-            .finalize(Cell::Wall, Cell::Empty);
-
-        let index = gen.get_random_room_index();
-        let entrance = gen.get_room_ranges(index);
-        let index = gen.get_random_room_index();
-        let exit = gen.get_room_ranges(index);
-
-        let pos = self.rand_empty_cell_pos(&mut grid, entrance.0, entrance.1);
-        grid[pos.0][pos.1] = Cell::Entrance;
-        let pos = self.rand_empty_cell_pos(&mut grid, exit.0, exit.1);
-        grid[pos.0][pos.1] = Cell::Exit;
+        let FloorLayout { mut grid, spawns } = self.generate_valid_layout();
 
         self.rand_place_effects(&mut grid);
-        let entities = self.rand_place_entities(&mut grid);
+        self.rand_place_items(&mut grid);
+        let entities = self.rand_place_entities(&grid, spawns);
+        let boss = self.create_boss();
 
-        Floor::new(self.level, self.rng, entities, grid)
+        let mut floor = Floor::new(self.level, self.rng, entities, grid);
+        if let Some((boss, minions)) = boss {
+            floor.add_boss(boss, minions);
+        }
+        floor
     }
 
     /// Permette di piazzare delle entità in modo casuale nell piano passato.\
-    /// Le entità verranno messe solamente sopra celle Empty e non sopvrapposte fra di loro.\
+    /// Prima vengono create le entità nei punti indicati da *spawns*, cercandole per nome nella
+    /// configurazione (vedi Config::find_spawn), dopodichè vengono piazzate quelle casuali.\
+    /// Un boss generato in questo modo chiude l'uscita del piano finchè non muore, come quelli
+    /// dei piani con un boss.\
+    /// Le entità casuali verranno messe solamente sopra celle Empty e non sopvrapposte fra di loro.\
+    /// Nel caso in cui nessuna entità della configurazione possa apparire nel livello, non ne viene
+    /// piazzata nessuna casuale.\
     /// Alla fine verrà restituito un vettore contenente tutte le entità che dovrà poi essere associato
     /// al piano in fase di creazione.
-    fn rand_place_entities(&mut self, grid: &mut Vec<Vec<Cell>>) -> Vec<Entity> {
-        let entities = ProbVec::new(&self.config.entities, |e| {
+    fn rand_place_entities(
+        &mut self,
+        grid: &[Vec<Cell>],
+        spawns: Vec<(Position, String)>,
+    ) -> Vec<Entity> {
+        let config = self.config;
+        let entities = ProbVec::new(&config.entities, |e| {
             e.floors.contains(&self.level).then(|| (e.priority, e))
         });
 
         let mut result: Vec<Entity> = vec![];
-        for _ in 0..self.config.entities_total {
-            let config = entities.sample(&mut self.rng).clone();
-            let mut entity = Entity::new(
-                config.name.clone(),
-                config.health,
-                config.attack,
-                config.behavior.clone(),
-            );
+        for (pos, name) in spawns {
+            if let Some((config, boss)) = config.find_spawn(&name) {
+                let mut entity = self.create_entity(config);
+                entity.position = pos;
+                if boss {
+                    entity.boss = true;
+                    entity.follow_stairs = false;
+                }
+                result.push(entity);
+            }
+        }
+
+        let total = if entities.is_empty() {
+            0
+        } else {
+            config.entities_total
+        };
+        for _ in 0..total {
+            let config = entities.sample(&mut self.rng);
+            let mut entity = self.create_entity(config);
 
             loop {
                 let pos = self.rand_empty_cell_pos(grid, 0..self.size, 0..self.size);
@@ -109,38 +128,618 @@ impl<'a> Generator<'a> {
         }
         result
     }
+    /// Crea il boss del piano e i suoi seguaci nel caso in cui il livello sia un piano con un boss.\
+    /// Viene utilizzato il primo boss della configurazione che può apparire nel livello.
+    fn create_boss(&mut self) -> Option<(Entity, Vec<Entity>)> {
+        let config = self.config;
+        let boss = config
+            .bosses
+            .iter()
+            .find(|boss| boss.is_boss_floor(self.level))?;
+
+        let mut entity = self.create_entity(&boss.boss);
+        entity.follow_stairs = false;
+        let minions = boss
+            .minions
+            .iter()
+            .map(|minion| self.create_entity(minion))
+            .collect();
+        Some((entity, minions))
+    }
+    /// Crea una nuova entità a partire dalla sua configurazione, con un seed casuale.
+    fn create_entity(&mut self, config: &ConfigEntity) -> Entity {
+        let mut entity = Entity::new_with(
+            config.name.clone(),
+            config.health,
+            config.get_stats(),
+            config.growth.clone(),
+            config.behavior.clone(),
+        );
+        entity.reseed(self.rng.gen());
+        entity.follow_stairs = config.follow_stairs;
+        entity
+    }
     /// piazza gli effetti della confgurazione in modo casuale su tutto il piano.\
-    /// essi vengono piazzati solamente sulle celle Empty
-    fn rand_place_effects(&mut self, grid: &mut Vec<Vec<Cell>>) {
+    /// essi vengono piazzati solamente sulle celle Empty, e solo se almeno uno può apparire nel livello
+    fn rand_place_effects(&mut self, grid: &mut [Vec<Cell>]) {
         let effects = ProbVec::new(&self.config.effects, |e| {
             e.floors.contains(&self.level).then(|| (e.priority, e))
         });
 
-        for _ in 0..self.config.effects_total {
+        let total = if effects.is_empty() {
+            0
+        } else {
+            self.config.effects_total
+        };
+        for _ in 0..total {
             let effect = effects.sample(&mut self.rng).effect.clone();
             let cell = Cell::Special(effect);
             let pos = self.rand_empty_cell_pos(grid, 0..self.size, 0..self.size);
             grid[pos.0][pos.1] = cell;
         }
     }
+    /// piazza gli oggetti della configurazione in modo casuale su tutto il piano.\
+    /// essi vengono piazzati solamente sulle celle Empty, e solo se almeno uno può apparire nel livello
+    fn rand_place_items(&mut self, grid: &mut [Vec<Cell>]) {
+        let items = ProbVec::new(&self.config.items, |i| {
+            i.floors.contains(&self.level).then_some((i.priority, i))
+        });
+
+        let total = if items.is_empty() {
+            0
+        } else {
+            self.config.items_total
+        };
+        for _ in 0..total {
+            let item = items.sample(&mut self.rng).item.clone();
+            let pos = self.rand_empty_cell_pos(grid, 0..self.size, 0..self.size);
+            grid[pos.0][pos.1] = Cell::Item(item);
+        }
+    }
+    /// Genera la struttura del piano finchè l'uscita non è raggiungibile dall'entrata con un percorso
+    /// lungo almeno *min_stairs_distance* celle, come indicato nella configurazione.\
+    /// Dato che ogni tentativo utilizza il generatore di numeri casuali del piano, la struttura
+    /// ottenuta dipende solamente dal seed del piano.\
+    /// Dopo MAX_GENERATION_ATTEMPTS tentativi viene accettata la struttura raggiungibile con le scale
+    /// più distanti; se invece l'uscita non è mai stata raggiungibile il metodo va in panic.
+    fn generate_valid_layout(&mut self) -> FloorLayout {
+        let maze_gen = &self.config.maze_generation;
+        let mut best: Option<(usize, FloorLayout)> = None;
+        for _ in 0..MAX_GENERATION_ATTEMPTS {
+            let layout =
+                maze_gen
+                    .generator
+                    .generate(self.size, self.level, maze_gen, &mut self.rng);
+            let Some(distance) = stairs_distance(&layout.grid) else {
+                continue;
+            };
+            if distance >= maze_gen.min_stairs_distance {
+                return layout;
+            }
+            if best.as_ref().is_none_or(|(max, _)| distance > *max) {
+                best = Some((distance, layout));
+            }
+        }
+
+        let (_, layout) = best.expect("Generator should create floors with a reachable exit!");
+        layout
+    }
+
     /// piazza una cella in un punto casuale tra i range inseriti.\
     /// il metodo continua a provare a piazzare la cella finche non trova una cella Empty.
     fn rand_empty_cell_pos(
         &mut self,
-        grid: &mut Vec<Vec<Cell>>,
+        grid: &[Vec<Cell>],
         range_x: Range<usize>,
         range_y: Range<usize>,
     ) -> Position {
-        loop {
-            let x = self.rng.gen_range(range_x.clone());
-            let y = self.rng.gen_range(range_y.clone());
-            if let Cell::Empty = grid[x][y] {
-                return Position(x, y);
+        rand_empty_cell_pos(&mut self.rng, grid, range_x, range_y)
+    }
+}
+
+/// Calcola la lunghezza del percorso più breve tra l'entrata e l'uscita della griglia,
+/// considerando percorribili tutte le celle che non sono muri.\
+/// Viene ritornato None nel caso in cui l'entrata o l'uscita non esistano o non siano collegate.
+fn stairs_distance(grid: &[Vec<Cell>]) -> Option<usize> {
+    let size = grid.len();
+    let find = |target: fn(&Cell) -> bool| {
+        (0..size)
+            .flat_map(|x| (0..size).map(move |y| Position(x, y)))
+            .find(|pos| target(&grid[pos.0][pos.1]))
+    };
+    let entrance = find(|cell| matches!(cell, Cell::Entrance))?;
+    let exit = find(|cell| matches!(cell, Cell::Exit))?;
+
+    let mut distances = vec![vec![None; size]; size];
+    distances[entrance.0][entrance.1] = Some(0);
+    let mut queue = VecDeque::from([entrance]);
+    while let Some(pos) = queue.pop_front() {
+        let distance = distances[pos.0][pos.1]?;
+        if pos == exit {
+            return Some(distance);
+        }
+        for direction in Direction::all() {
+            let mut next = pos;
+            direction.move_from(&mut next);
+            if next.0 < size
+                && next.1 < size
+                && distances[next.0][next.1].is_none()
+                && !matches!(grid[next.0][next.1], Cell::Wall)
+            {
+                distances[next.0][next.1] = Some(distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Cerca una posizione casuale tra i range inseriti in cui si trova una cella Empty.\
+/// Il metodo continua a provare finche non trova una cella Empty, quindi nei range
+/// deve essercene almeno una.
+fn rand_empty_cell_pos(
+    rng: &mut Pcg32,
+    grid: &[Vec<Cell>],
+    range_x: Range<usize>,
+    range_y: Range<usize>,
+) -> Position {
+    loop {
+        let x = rng.gen_range(range_x.clone());
+        let y = rng.gen_range(range_y.clone());
+        if let Cell::Empty = grid[x][y] {
+            return Position(x, y);
+        }
+    }
+}
+
+/// Trait che permette di implementare un algoritmo per la generazione della struttura di un piano.\
+/// Il trait è taggato con typetag in modo che possa essere utilizzato nella configurazione
+/// e quindi scelto tramite serializzazione e deserializzazione di serde, come per Effect e Behavior.\
+/// \
+/// La griglia generata deve essere quadrata con il lato indicato, avere tutto il bordo
+/// fatto di muri e contenere esattamente una Entrance e una Exit, collegate da un percorso
+/// di celle che non sono muri.\
+/// Le altre celle dovrebbero essere Wall o Empty, dato che effetti, oggetti ed entità casuali
+/// vengono inseriti in seguito dal Generator; fanno eccezione quelle delle stanze prefabbricate.\
+/// Il lato passato è sempre almeno MIN_FLOOR_SIZE, dato che Config::validate scarta le
+/// configurazioni con piani più piccoli.
+#[typetag::serde(tag = "type")]
+pub trait FloorGenerator: DynClone + core::fmt::Debug {
+    /// Genera la struttura del piano del livello indicato utilizzando il generatore di numeri
+    /// casuali passato, in modo che la generazione sia ripetibile a partire dal seed del piano.\
+    /// La configurazione permette di accedere ai parametri comuni a tutti i generatori.
+    fn generate(
+        &self,
+        size: usize,
+        level: usize,
+        config: &ConfigMaze,
+        rng: &mut Pcg32,
+    ) -> FloorLayout;
+    /// Controlla che i parametri del generatore permettano di generare dei piani.\
+    /// Nel caso in cui non sia così viene restituita una descrizione del problema.\
+    /// Di base i parametri sono sempre validi.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+clone_trait_object!(FloorGenerator);
+
+/// Struttura di un piano creata da un FloorGenerator.\
+/// *spawns* indica le posizioni in cui devono essere create delle entità specifiche,
+/// identificate dal nome che hanno nella configurazione.
+#[derive(Clone, Debug)]
+pub struct FloorLayout {
+    pub grid: Vec<Vec<Cell>>,
+    pub spawns: Vec<(Position, String)>,
+}
+
+impl From<Vec<Vec<Cell>>> for FloorLayout {
+    fn from(grid: Vec<Vec<Cell>>) -> Self {
+        Self {
+            grid,
+            spawns: vec![],
+        }
+    }
+}
+
+/// Generatore originale del gioco, formato da stanze collegate tra di loro da un labirinto.\
+/// Utilizza i parametri di ConfigMaze per le stanze e il labirinto (vedi MazeGenerator),
+/// e mette entrata e uscita in due stanze casuali e diverse, se ne esistono almeno due.\
+/// Prima delle stanze casuali vengono inserite le stanze prefabbricate scelte per il livello,
+/// nelle quali non verranno messe nè l'entrata nè l'uscita.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoomsAndMazes;
+
+#[typetag::serde]
+impl FloorGenerator for RoomsAndMazes {
+    fn generate(
+        &self,
+        size: usize,
+        level: usize,
+        config: &ConfigMaze,
+        rng: &mut Pcg32,
+    ) -> FloorLayout {
+        let prefabs = choose_prefabs(&config.prefabs, level, rng);
+        let mut gen = MazeGenerator::new(size, config.room_size.clone(), rng);
+        let placed = prefabs
+            .into_iter()
+            .filter_map(|prefab| {
+                let (width, height) = (prefab.get_width(), prefab.get_height());
+                let index = gen.place_room(width, height, config.room_placing_attempts)?;
+                Some((gen.get_room_ranges(index), prefab))
+            })
+            .collect::<Vec<_>>();
+        let mut grid = gen
+            .generate_rooms(config.room_placing_attempts)
+            .generate_labyrinth(config.straight_percentage)
+            .connect_regions()
+            .remove_dead_ends(config.dead_ends)
+            .finalize(Cell::Wall, Cell::Empty);
+
+        let index = gen.get_random_room_index();
+        let entrance = gen.get_room_ranges(index);
+        let mut other = gen.get_random_room_index();
+        while other == index && gen.get_rooms_count() > 1 {
+            other = gen.get_random_room_index();
+        }
+        let exit = gen.get_room_ranges(other);
+
+        let pos = rand_empty_cell_pos(rng, &grid, entrance.0, entrance.1);
+        grid[pos.0][pos.1] = Cell::Entrance;
+        let pos = rand_empty_cell_pos(rng, &grid, exit.0, exit.1);
+        grid[pos.0][pos.1] = Cell::Exit;
+
+        let mut spawns = vec![];
+        for ((range_x, range_y), prefab) in placed {
+            let top_left = Position(range_x.start, range_y.end - 1);
+            spawns.extend(place_prefab(&mut grid, prefab, top_left));
+        }
+        FloorLayout { grid, spawns }
+    }
+}
+
+/// Generatore di caverne tramite automi cellulari.\
+/// Inizialmente ogni cella interna diventa un muro con probabilità *fill_percentage* (0..=100),
+/// dopodichè per *iterations* volte ogni cella diventa un muro se almeno 5 delle 9 celle
+/// attorno ad essa (compresa se stessa) sono muri, altrimenti diventa vuota.\
+/// Alla fine viene tenuta solamente la caverna più grande, in cui vengono messe entrata e uscita.\
+/// Nel caso in cui dopo CAVE_ATTEMPTS tentativi la caverna più grande sia ancora troppo piccola
+/// (ad esempio perchè i parametri riempiono tutto di muri), il piano viene generato con DrunkardWalk.\
+/// https://www.roguebasin.com/index.php/Cellular_Automata_Method_for_Generating_Random_Cave-Like_Levels
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CellularCaves {
+    pub fill_percentage: u32,
+    pub iterations: u32,
+}
+
+/// Numero massimo di caverne generate da CellularCaves prima di usare un altro generatore.
+pub const CAVE_ATTEMPTS: usize = 100;
+
+impl Default for CellularCaves {
+    fn default() -> Self {
+        Self {
+            fill_percentage: 45,
+            iterations: 4,
+        }
+    }
+}
+
+#[typetag::serde]
+impl FloorGenerator for CellularCaves {
+    fn generate(
+        &self,
+        size: usize,
+        level: usize,
+        config: &ConfigMaze,
+        rng: &mut Pcg32,
+    ) -> FloorLayout {
+        let fill = self.fill_percentage.min(100) as f64 / 100.0;
+        for _ in 0..CAVE_ATTEMPTS {
+            let inner = |x: usize, y: usize| x > 0 && y > 0 && x < size - 1 && y < size - 1;
+            let mut walls = (0..size)
+                .map(|x| {
+                    (0..size)
+                        .map(|y| !inner(x, y) || rng.gen_bool(fill))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            for _ in 0..self.iterations {
+                walls = (0..size)
+                    .map(|x| {
+                        (0..size)
+                            .map(|y| {
+                                if !inner(x, y) {
+                                    return true;
+                                }
+                                let count = walls[x - 1..x + 2]
+                                    .iter()
+                                    .flat_map(|col| &col[y - 1..y + 2])
+                                    .filter(|wall| **wall)
+                                    .count();
+                                count >= 5
+                            })
+                            .collect()
+                    })
+                    .collect();
+            }
+
+            let mut grid = walls
+                .into_iter()
+                .map(|col| {
+                    col.into_iter()
+                        .map(|wall| if wall { Cell::Wall } else { Cell::Empty })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            if keep_largest_region(&mut grid) >= 2 {
+                place_stairs(rng, &mut grid);
+                return grid.into();
+            }
+        }
+        DrunkardWalk::default().generate(size, level, config, rng)
+    }
+    fn validate(&self) -> Result<(), String> {
+        match self.fill_percentage < 100 {
+            true => Ok(()),
+            false => Err(format!(
+                "fill_percentage must be below 100 (found {})",
+                self.fill_percentage
+            )),
+        }
+    }
+}
+
+/// Generatore di stanze tramite partizionamento binario dello spazio (BSP).\
+/// Lo spazio del piano viene diviso ricorsivamente in due parti finchè le parti sono
+/// abbastanza grandi da contenere due stanze; in ogni parte finale viene creata una stanza
+/// con la grandezza indicata in ConfigMaze, e le stanze di due parti sorelle vengono collegate
+/// tramite un corridoio.\
+/// Entrata e uscita vengono messe in due celle vuote casuali.\
+/// https://www.roguebasin.com/index.php/Basic_BSP_Dungeon_generation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BspRooms;
+
+impl BspRooms {
+    /// Divide ricorsivamente l'area indicata (estremi inclusi) e crea le stanze nelle aree finali.\
+    /// Viene ritornata la posizione del centro di una delle stanze create, in modo da
+    /// poterla collegare con le stanze dell'area sorella.
+    fn split(
+        rng: &mut Pcg32,
+        grid: &mut [Vec<Cell>],
+        lo: Position,
+        hi: Position,
+        rooms: &Range<usize>,
+    ) -> Position {
+        let min_room = rooms.start.max(1);
+        let max_room = rooms.end.saturating_sub(1).max(min_room);
+        let min = min_room + 1;
+        let width = hi.0 - lo.0 + 1;
+        let height = hi.1 - lo.1 + 1;
+        let split_x = width > 2 * min;
+        let split_y = height > 2 * min;
+
+        if !split_x && !split_y {
+            let room_w = rng.gen_range(min_room.min(width)..=max_room.min(width));
+            let room_h = rng.gen_range(min_room.min(height)..=max_room.min(height));
+            let x = rng.gen_range(lo.0..=hi.0 + 1 - room_w);
+            let y = rng.gen_range(lo.1..=hi.1 + 1 - room_h);
+            for col in grid.iter_mut().skip(x).take(room_w) {
+                for cell in col.iter_mut().skip(y).take(room_h) {
+                    *cell = Cell::Empty;
+                }
+            }
+            return Position(x + room_w / 2, y + room_h / 2);
+        }
+
+        let vertical = match (split_x, split_y) {
+            (true, true) => rng.gen_bool(0.5),
+            (split_x, _) => split_x,
+        };
+        let (first, second) = if vertical {
+            let mid = rng.gen_range(lo.0 + min..=hi.0 - min);
+            (
+                Self::split(rng, grid, lo, Position(mid - 1, hi.1), rooms),
+                Self::split(rng, grid, Position(mid + 1, lo.1), hi, rooms),
+            )
+        } else {
+            let mid = rng.gen_range(lo.1 + min..=hi.1 - min);
+            (
+                Self::split(rng, grid, lo, Position(hi.0, mid - 1), rooms),
+                Self::split(rng, grid, Position(lo.0, mid + 1), hi, rooms),
+            )
+        };
+
+        carve_corridor(grid, first, second);
+        if rng.gen_bool(0.5) {
+            first
+        } else {
+            second
+        }
+    }
+}
+
+#[typetag::serde]
+impl FloorGenerator for BspRooms {
+    fn generate(
+        &self,
+        size: usize,
+        _level: usize,
+        config: &ConfigMaze,
+        rng: &mut Pcg32,
+    ) -> FloorLayout {
+        let mut grid = vec![vec![Cell::Wall; size]; size];
+        let lo = Position(1, 1);
+        let hi = Position(size - 2, size - 2);
+        Self::split(rng, &mut grid, lo, hi, &config.room_size);
+        place_stairs(rng, &mut grid);
+        grid.into()
+    }
+}
+
+/// Generatore che scava il piano tramite una camminata casuale (drunkard's walk).\
+/// Partendo dal centro del piano ci si muove in direzioni casuali scavando le celle attraversate,
+/// finchè la percentuale *coverage* (0..=100) delle celle interne non è stata scavata.\
+/// Dato che la camminata è unica, tutte le celle scavate sono collegate fra di loro.\
+/// https://www.roguebasin.com/index.php/Random_Walk_Cave_Generation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DrunkardWalk {
+    pub coverage: u32,
+}
+
+impl Default for DrunkardWalk {
+    fn default() -> Self {
+        Self { coverage: 40 }
+    }
+}
+
+#[typetag::serde]
+impl FloorGenerator for DrunkardWalk {
+    fn generate(
+        &self,
+        size: usize,
+        _level: usize,
+        _config: &ConfigMaze,
+        rng: &mut Pcg32,
+    ) -> FloorLayout {
+        let mut grid = vec![vec![Cell::Wall; size]; size];
+        let inner = (size - 2) * (size - 2);
+        let target = (inner * self.coverage.min(100) as usize / 100).clamp(2, inner);
+
+        let mut pos = Position(size / 2, size / 2);
+        let mut carved = 0;
+        while carved < target {
+            if let Cell::Wall = grid[pos.0][pos.1] {
+                grid[pos.0][pos.1] = Cell::Empty;
+                carved += 1;
+            }
+
+            let mut next = pos;
+            Direction::random(rng).move_from(&mut next);
+            if (1..size - 1).contains(&next.0) && (1..size - 1).contains(&next.1) {
+                pos = next;
             }
         }
+
+        place_stairs(rng, &mut grid);
+        grid.into()
     }
 }
 
+/// Sceglie le stanze prefabbricate da inserire nel piano del livello indicato,
+/// tirando a caso la probabilità di ognuna di quelle disponibili per il livello.
+fn choose_prefabs<'a>(
+    prefabs: &'a [ConfigPrefab],
+    level: usize,
+    rng: &mut Pcg32,
+) -> Vec<&'a Prefab> {
+    prefabs
+        .iter()
+        .filter(|config| config.floors.contains(&level))
+        .filter(|config| rng.gen_range(0..100) < config.chance)
+        .map(|config| &config.prefab)
+        .collect()
+}
+
+/// Copia le celle della stanza prefabbricata nella griglia, a partire dalla posizione in alto a
+/// sinistra indicata, senza sovrascrivere l'entrata e l'uscita.\
+/// Vengono ritornati i punti in cui devono essere create le entità della stanza.
+fn place_prefab(
+    grid: &mut [Vec<Cell>],
+    prefab: &Prefab,
+    top_left: Position,
+) -> Vec<(Position, String)> {
+    let mut spawns = vec![];
+    for (col, row, tile) in prefab.get_tiles() {
+        let pos = Position(top_left.0 + col, top_left.1 - row);
+        if matches!(grid[pos.0][pos.1], Cell::Entrance | Cell::Exit) {
+            continue;
+        }
+        grid[pos.0][pos.1] = match tile {
+            PrefabTile::Wall => Cell::Wall,
+            PrefabTile::Empty => Cell::Empty,
+            PrefabTile::Effect(effect) => Cell::Special(effect),
+            PrefabTile::Item(item) => Cell::Item(item),
+            PrefabTile::Spawn(name) => {
+                spawns.push((pos, name));
+                Cell::Empty
+            }
+        };
+    }
+    spawns
+}
+
+/// Scava un corridoio a forma di L tra le due posizioni, prima in orizzontale e poi in verticale.
+fn carve_corridor(grid: &mut [Vec<Cell>], from: Position, to: Position) {
+    let (min_x, max_x) = (from.0.min(to.0), from.0.max(to.0));
+    for col in &mut grid[min_x..=max_x] {
+        col[from.1] = Cell::Empty;
+    }
+    let (min_y, max_y) = (from.1.min(to.1), from.1.max(to.1));
+    for cell in &mut grid[to.0][min_y..=max_y] {
+        *cell = Cell::Empty;
+    }
+}
+
+/// Mette entrata e uscita in due celle Empty casuali e diverse della griglia.\
+/// La griglia deve avere almeno due celle Empty.
+fn place_stairs(rng: &mut Pcg32, grid: &mut [Vec<Cell>]) {
+    let size = grid.len();
+    let pos = rand_empty_cell_pos(rng, grid, 0..size, 0..size);
+    grid[pos.0][pos.1] = Cell::Entrance;
+    let pos = rand_empty_cell_pos(rng, grid, 0..size, 0..size);
+    grid[pos.0][pos.1] = Cell::Exit;
+}
+
+/// Trova tutte le regioni di celle collegate che non sono muri e tiene solamente la più grande,
+/// trasformando in muri tutte le altre.\
+/// Viene ritornato il numero di celle della regione tenuta.
+fn keep_largest_region(grid: &mut [Vec<Cell>]) -> usize {
+    let size = grid.len();
+    let mut visited = vec![vec![false; size]; size];
+    let mut largest: Vec<Position> = vec![];
+
+    for x in 0..size {
+        for y in 0..size {
+            if visited[x][y] || matches!(grid[x][y], Cell::Wall) {
+                continue;
+            }
+
+            visited[x][y] = true;
+            let mut region = vec![];
+            let mut queue = VecDeque::from([Position(x, y)]);
+            while let Some(pos) = queue.pop_front() {
+                region.push(pos);
+                for direction in Direction::all() {
+                    let mut next = pos;
+                    direction.move_from(&mut next);
+                    if next.0 < size
+                        && next.1 < size
+                        && !visited[next.0][next.1]
+                        && !matches!(grid[next.0][next.1], Cell::Wall)
+                    {
+                        visited[next.0][next.1] = true;
+                        queue.push_back(next);
+                    }
+                }
+            }
+            if region.len() > largest.len() {
+                largest = region;
+            }
+        }
+    }
+
+    let largest_set = largest.iter().collect::<HashSet<_>>();
+    for (x, col) in grid.iter_mut().enumerate() {
+        for (y, cell) in col.iter_mut().enumerate() {
+            if !largest_set.contains(&Position(x, y)) {
+                *cell = Cell::Wall;
+            }
+        }
+    }
+    largest.len()
+}
+
 pub struct ProbVec<'a, T> {
     prob: Vec<(f32, &'a T)>,
 }
@@ -173,15 +772,21 @@ impl<'a, T> ProbVec<'a, T> {
         Self { prob }
     }
 
+    /// Indica se nessun elemento ha superato il filtro, e quindi non è possibile prenderne uno.
+    pub fn is_empty(&self) -> bool {
+        self.prob.is_empty()
+    }
+
     /// Dato un vettore generato secondo la funzione vec_filter, essa ne prende un valore casuale
     /// utilizzando le probabilità interne del vettore.\
+    /// Nel caso in cui il vettore sia vuoto (vedi is_empty) questo metodo va in panic.
     pub fn sample(&self, rng: &mut impl Rng) -> &'a T {
         let sample = rng.gen_range(0.0..1.0);
         self.prob
             .iter()
-            .filter(|(p, _)| *p >= sample)
-            .next()
-            .unwrap()
+            .find(|(p, _)| *p >= sample)
+            .or(self.prob.last())
+            .expect("Cannot sample from an empty ProbVec!")
             .1
     }
 }
@@ -202,6 +807,7 @@ pub struct MazeGenerator<'a> {
     rooms_size: Range<usize>,
     rng: &'a mut Pcg32,
     rooms: Vec<Room>,
+    reserved: usize,
     regions: Vec<Vec<Option<usize>>>,
     current_region: usize,
 }
@@ -239,6 +845,7 @@ impl<'a> MazeGenerator<'a> {
             rooms_size,
             rng,
             rooms: vec![],
+            reserved: 0,
             regions: vec![vec![None; size]; size],
             current_region: 0,
         }
@@ -436,14 +1043,37 @@ impl<'a> MazeGenerator<'a> {
             if self.rooms.iter().any(|other| room.collide(other)) {
                 attempts -= 1;
             } else {
-                self.current_region += 1;
-                room.get_area_points()
-                    .for_each(|p| self.set(&p, Some(self.current_region)));
-                self.rooms.push(room);
+                self.add_room(room);
             }
         }
         self
     }
+    /// Aggiunge una stanza riservata della grandezza indicata in un punto casuale, ad esempio
+    /// per inserire una stanza prefabbricata.\
+    /// Le dimensioni vengono arrotondate al numero dispari successivo, e la stanza viene
+    /// scartata se dopo *attempts* tentativi non è stato possibile inserirla senza collisioni.\
+    /// Questo metodo deve essere chiamato prima della generazione delle stanze casuali e viene
+    /// ritornato l'indice della stanza inserita, se esiste.\
+    /// Le stanze riservate non vengono mai scelte da get_random_room_index, a meno che non ci
+    /// siano altre stanze.
+    pub fn place_room(&mut self, width: usize, height: usize, attempts: u32) -> Option<usize> {
+        for _ in 0..attempts {
+            let room = Room::rand_sized(self.rng, self.size, width, height)?;
+            if !self.rooms.iter().any(|other| room.collide(other)) {
+                self.add_room(room);
+                self.reserved += 1;
+                return Some(self.rooms.len() - 1);
+            }
+        }
+        None
+    }
+    /// Aggiunge la stanza alla lista e assegna una nuova regione a tutti i suoi punti.
+    fn add_room(&mut self, room: Room) {
+        self.current_region += 1;
+        room.get_area_points()
+            .for_each(|p| self.set(&p, Some(self.current_region)));
+        self.rooms.push(room);
+    }
     /// Ritorna un iteratore di posizioni vicine alla posizione indicata.\
     /// Viene ritornato un iteratore in modo che si possa decidere cosa farlo diventare.\
     /// Nel caso una posizione sia fuori dal campo, essa viene scartata e non
@@ -477,7 +1107,16 @@ impl<'a> MazeGenerator<'a> {
     }
     /// Ritorna un indice a caso fra quelli possibili riguardo le stanze create.
     pub fn get_random_room_index(&mut self) -> usize {
-        self.rng.gen_range(0..self.rooms.len())
+        let first = if self.rooms.len() > self.reserved {
+            self.reserved
+        } else {
+            0
+        };
+        self.rng.gen_range(first..self.rooms.len())
+    }
+    /// Ritorna il numero di stanze generate.
+    pub fn get_rooms_count(&self) -> usize {
+        self.rooms.len()
     }
     /// Ritorna una coppia di ranges che indicano la zona in cui si trova la stanza indicata fra quelle generate.
     pub fn get_room_ranges(&self, index: usize) -> (Range<usize>, Range<usize>) {
@@ -522,6 +1161,23 @@ impl Room {
             hi: Position(x_top, y_top),
         }
     }
+    /// Crea una stanza con la grandezza indicata in un punto casuale all'interno dei muri esterni.\
+    /// Le dimensioni vengono arrotondate al numero dispari successivo, e nel caso in cui la stanza
+    /// sia troppo grande per il piano viene ritornato None.
+    pub fn rand_sized(rng: &mut impl Rng, max: usize, width: usize, height: usize) -> Option<Self> {
+        let width = width.max(1) | 1;
+        let height = height.max(1) | 1;
+        if width + 2 > max || height + 2 > max {
+            return None;
+        }
+
+        let x = Self::rand_odd(rng, 1..max - width);
+        let y = Self::rand_odd(rng, 1..max - height);
+        Some(Self {
+            lo: Position(x, y),
+            hi: Position(x + width - 1, y + height - 1),
+        })
+    }
     /// Genera tutti i punti di tutti i lati all'esterno della stanza, insomma i punti dei muri.\
     /// Gli unici punti non generati dall'iteratore ritornato sono quelli degli angoli.\
     /// Es. dato lo(1,1) e hi(2,2) => (1,0), (1,3), (2,0), (2,3), (0,1), (3,1), (0,2), (3,2)\
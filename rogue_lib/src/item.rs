@@ -0,0 +1,108 @@
+use super::{cell::Effect, entities::Entity, floor::Floor};
+use dyn_clone::{clone_trait_object, DynClone};
+use serde::{Deserialize, Serialize};
+
+/// Trait che permette di implementare un oggetto che si può trovare nel piano
+/// e che le entità possono raccogliere nel loro inventario.\
+/// Il trait è taggato con typetag in modo che possa essere utilizzato
+/// nella serializzazione e deserializzazione di serde.\
+/// Quello che viene richiesto è che, nell'implementazione di una
+/// struttura concreta di questo trait, venga messo sopra impl X for Item:\
+/// #\[typetag::serde\]\
+/// \
+/// In questo modo si possono creare molteplici oggetti che implementano
+/// questo trait senza il bisogno di avere un Enum con essi
+#[typetag::serde(tag = "type")]
+pub trait Item: DynClone + core::fmt::Debug {
+    /// Restituisce il nome dell'oggetto in modo da poterlo mostrare nell'inventario.
+    fn get_name(&self) -> String;
+    /// Utilizza l'oggetto sull'entità che lo possiede.\
+    /// Il valore ritornato indica se l'oggetto è stato consumato, e quindi
+    /// se deve essere rimosso dall'inventario oppure no.
+    fn use_on(&self, entity: &mut Entity, floor: &mut Floor) -> bool;
+    /// Indica di quanto aumenta l'attacco dell'entità che possiede l'oggetto.\
+    /// Di base un oggetto non modifica l'attacco.
+    fn attack_bonus(&self) -> i32 {
+        0
+    }
+    /// Ritorna un carattere che rappresenta l'oggetto.
+    fn as_char(&self) -> char {
+        '*'
+    }
+}
+clone_trait_object!(Item);
+
+/// Pozione che, una volta bevuta, applica l'effetto contenuto all'entità.\
+/// Può contenere qualunque effetto, ad esempio InstantDamage per una cura istantanea
+/// o Regeneration per una cura nel tempo.\
+/// Una volta utilizzata la pozione viene consumata.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Potion {
+    name: String,
+    effect: Box<dyn Effect>,
+}
+impl Potion {
+    pub fn new(name: String, effect: Box<dyn Effect>) -> Self {
+        Self { name, effect }
+    }
+}
+#[typetag::serde]
+impl Item for Potion {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+    fn use_on(&self, entity: &mut Entity, _floor: &mut Floor) -> bool {
+        entity.add_effect(self.effect.clone());
+        true
+    }
+    fn as_char(&self) -> char {
+        '!'
+    }
+}
+
+/// Chiave identificata da un nome.\
+/// Non fa nulla se utilizzata, ma la sua presenza nell'inventario può essere
+/// controllata per aprire porte o passaggi.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Key(pub String);
+#[typetag::serde]
+impl Item for Key {
+    fn get_name(&self) -> String {
+        format!("Key ({})", self.0)
+    }
+    fn use_on(&self, _entity: &mut Entity, _floor: &mut Floor) -> bool {
+        false
+    }
+    fn as_char(&self) -> char {
+        'k'
+    }
+}
+
+/// Arma che aumenta l'attacco di chi la possiede.\
+/// Nel caso in cui l'entità abbia più armi, verrà utilizzata quella con il bonus maggiore.\
+/// L'arma non viene mai consumata.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Weapon {
+    name: String,
+    attack: i32,
+}
+impl Weapon {
+    pub fn new(name: String, attack: i32) -> Self {
+        Self { name, attack }
+    }
+}
+#[typetag::serde]
+impl Item for Weapon {
+    fn get_name(&self) -> String {
+        format!("{} (+{})", self.name, self.attack)
+    }
+    fn use_on(&self, _entity: &mut Entity, _floor: &mut Floor) -> bool {
+        false
+    }
+    fn attack_bonus(&self) -> i32 {
+        self.attack
+    }
+    fn as_char(&self) -> char {
+        '/'
+    }
+}
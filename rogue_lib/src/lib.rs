@@ -1,19 +1,40 @@
 use self::{
     cell::Cell,
     config::Config,
-    entities::{Action, Behavior, Direction, Entity},
+    entities::{Action, Behavior, Direction, Entity, Position},
     floor::FloorView,
     game::Dungeon,
+    save::SaveFormat,
+    score::{HighScores, Score, HIGH_SCORES_FILE},
+    tui::{LookMap, Screen},
 };
+use rand_pcg::Pcg32;
 use serde::{Deserialize, Serialize};
-use std::io::Write;
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
 
 pub mod cell;
 pub mod config;
 pub mod entities;
+pub mod event;
 pub mod floor;
 pub mod game;
 pub mod generator;
+pub mod item;
+pub mod prefab;
+pub mod remote;
+pub mod replay;
+pub mod save;
+pub mod score;
+pub mod simulation;
+pub mod snapshot;
+pub mod status;
+pub mod tui;
 
 /** Es.3
  * Implementare una libreria che permetta di realizzare il seguente gioco.
@@ -48,15 +69,233 @@ pub mod generator;
  * Se volete potete anche cambiare le regole del gioco.
  * Mettere main e definizioni in files separati (le definizioni in uno o più files) e scrivete i test in una directory a parte.
  */
-pub fn run_console(player: String, seed: u64) {
-    let mut config = Config::default();
-    config.game_seed = seed;
-
-    let mut game = Dungeon::new_with(config);
-    game.add_player(player, Box::new(ConsoleInput));
+pub fn run_console(options: ConsoleOptions) {
+    let console = ConsoleInput::new();
+    let Some((mut game, resumed)) = start_console_game(&options, &console) else {
+        return;
+    };
+    let Some(name) = game.get_players().next().map(|p| p.get_name().clone()) else {
+        return;
+    };
 
+    let save = options.save.as_deref();
+    let mut saved = save.is_some() && resumed == save;
     while game.has_players() {
-        game.compute_turn();
+        console.prepare_turn(&game, &name);
+        if !play_console_turn(&mut game, console.get_save_request(), save, options.format) {
+            return;
+        }
+        saved |= game.get_floor_changes().next().is_some();
+    }
+    if let Some(file) = save.filter(|_| saved) {
+        let _ = std::fs::remove_file(file);
+    }
+
+    let key = HighScores::key(game.get_config());
+    if let Some(high_scores) = update_high_scores(&game, &key, HIGH_SCORES_FILE) {
+        println!(
+            "{}",
+            ConsoleInput::high_scores_as_string(high_scores.get(&key))
+        );
+    }
+}
+
+/// Calcola un turno della partita giocata nella console e la salva nel file *save*, se indicato.\
+/// La partita viene salvata quando almeno un giocatore cambia piano (vedi Dungeon::get_floor_changes)
+/// e quando il giocatore chiede di uscire salvando tramite ConsoleInput (vedi *save_request*).\
+/// La richiesta viene controllata prima di calcolare il turno, in modo da salvare la partita
+/// esattamente come il giocatore l'ha lasciata; in questo caso il turno non viene calcolato,
+/// la richiesta viene consumata e viene restituito false, dato che la partita non deve essere
+/// continuata.
+pub fn play_console_turn(
+    game: &mut Dungeon,
+    save_request: &AtomicBool,
+    save: Option<&str>,
+    format: SaveFormat,
+) -> bool {
+    if save_request.swap(false, Ordering::Relaxed) {
+        match save {
+            Some(file) => save_console_game(game, file, format),
+            None => eprintln!("No save file was set, the game was not saved"),
+        }
+        return false;
+    }
+
+    game.compute_turn();
+    if let Some(file) = save.filter(|_| game.get_floor_changes().next().is_some()) {
+        save_console_game(game, file, format);
+    }
+    true
+}
+
+/// Aggiunge i punteggi della partita alla classifica salvata nel file indicato e la salva.\
+/// Nel caso in cui il file esista ma non possa essere letto (ad esempio perchè danneggiato o
+/// di un formato precedente), la classifica non viene sovrascritta e viene mostrato l'errore.\
+/// Restituisce la classifica aggiornata, oppure None se non è stato possibile caricarla.
+pub fn update_high_scores(game: &Dungeon, key: &str, file: &str) -> Option<HighScores> {
+    let mut high_scores = match HighScores::load(file) {
+        Ok(high_scores) => high_scores,
+        Err(e) => {
+            eprintln!("Cannot load the high scores from {file}: {e}");
+            return None;
+        }
+    };
+    for score in game.get_scores() {
+        high_scores.add(key.to_string(), score.clone());
+    }
+    if let Err(e) = high_scores.save(file) {
+        eprintln!("Cannot save the high scores to {file}: {e}");
+    }
+    Some(high_scores)
+}
+
+/// Crea la partita da giocare nella console a partire dalle opzioni indicate.\
+/// La partita viene caricata dal file *load* se indicato; altrimenti, nel caso in cui esista
+/// un salvataggio nel file *save*, viene chiesto al giocatore se vuole riprenderlo.\
+/// I giocatori di una partita caricata vengono ricollegati alla console tramite una copia di
+/// *console*, dato che il suo stato non viene salvato.\
+/// Insieme alla partita viene restituito il file da cui è stata ripresa, se presente.\
+/// Nel caso in cui il caricamento fallisca viene mostrato l'errore e restituito None.
+fn start_console_game<'a>(
+    options: &'a ConsoleOptions,
+    console: &ConsoleInput,
+) -> Option<(Dungeon, Option<&'a str>)> {
+    let input = || Box::new(console.clone());
+    let resume = match &options.load {
+        Some(file) => Some(file.as_str()),
+        None => options.save.as_deref().filter(|file| {
+            Path::new(file).exists() && {
+                let term = console::Term::stdout();
+                let question = format!("Found a saved game in {file}, resume it? [y/n] ");
+                let _ = term.write_str(&question);
+                let answer = term.read_char().unwrap_or('n');
+                let _ = term.write_line("");
+                answer == 'y'
+            }
+        }),
+    };
+
+    match resume {
+        Some(file) => match Dungeon::load(file) {
+            Ok(mut game) => {
+                let names = game.get_players().map(|p| p.get_name().clone());
+                for name in names.collect::<Vec<_>>() {
+                    if let Some(player) = game.get_player_mut(&name) {
+                        player.set_behavior(input());
+                    }
+                }
+                Some((game, Some(file)))
+            }
+            Err(e) => {
+                eprintln!("Cannot load the game from {file}: {e}");
+                None
+            }
+        },
+        None => {
+            let mut game = Dungeon::new_with(options.config.clone());
+            game.add_player(options.player.clone(), input());
+            Some((game, None))
+        }
+    }
+}
+
+/// Salva la partita della console nel file indicato, mostrando un eventuale errore.
+fn save_console_game(game: &mut Dungeon, file: &str, format: SaveFormat) {
+    if let Err(e) = game.save(file, format) {
+        eprintln!("Cannot save the game to {file}: {e}");
+    }
+}
+
+/// File in cui viene salvata di base la partita giocata nella console.
+pub const CONSOLE_SAVE_FILE: &str = "savegame.json";
+
+/// Messaggio con le opzioni accettate dall'eseguibile della console (vedi ConsoleOptions::from_args).
+pub const CONSOLE_USAGE: &str = "Usage: rogue_lib [options]
+  --config <file>  load the configuration from a json file
+  --seed <number>  seed of the dungeon (random if missing)
+  --name <name>    name of the player (default: Player)
+  --load <file>    resume the game saved in the file
+  --save <file>    file used to save the game (default: the loaded file or savegame.json)
+  --no-save        never save the game
+  --binary         use the compact binary format for saving
+  --help           show this message";
+
+/// Opzioni con cui avviare una partita nella console.\
+/// *player* indica il nome del giocatore e *config* la configurazione del dungeon.\
+/// *load* indica il file da cui riprendere una partita salvata; in questo caso il nome e la
+/// configurazione vengono ignorati, dato che sono già presenti nel salvataggio.\
+/// *save* indica il file in cui salvare la partita, nel formato *format*: il salvataggio viene
+/// fatto automaticamente ad ogni cambio di piano e quando il giocatore esce salvando, mentre
+/// alla fine della partita il file viene cancellato, ma solo se la partita era stata salvata
+/// in esso o ripresa da esso. Se non viene indicato, la partita ripresa da *load*
+/// viene salvata nello stesso file da cui è stata caricata. All'avvio, se il file esiste e non
+/// è indicato *load*, viene chiesto al giocatore se vuole riprendere la partita salvata in esso.
+#[derive(Clone, Debug)]
+pub struct ConsoleOptions {
+    pub player: String,
+    pub config: Config,
+    pub load: Option<String>,
+    pub save: Option<String>,
+    pub format: SaveFormat,
+}
+
+impl ConsoleOptions {
+    /// Crea le opzioni a partire dagli argomenti passati all'eseguibile, escluso il nome di esso.\
+    /// La configurazione viene caricata dal file indicato (o è quella di base) e controllata
+    /// tramite Config::validate; il seed, se indicato, sostituisce quello della configurazione,
+    /// mentre se mancano entrambi ne viene scelto uno casuale.\
+    /// In caso di problemi viene ritornato un messaggio leggibile che li descrive.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        let mut options = Self {
+            player: "Player".to_string(),
+            config: Config::default(),
+            load: None,
+            save: Some(CONSOLE_SAVE_FILE.to_string()),
+            format: SaveFormat::Json,
+        };
+        let mut config = None;
+        let mut seed = None;
+        let mut save = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {arg}"));
+            match arg.as_str() {
+                "--config" => config = Some(value()?),
+                "--seed" => {
+                    let value = value()?;
+                    let parsed = value
+                        .parse()
+                        .map_err(|_| format!("Invalid seed: {value}"))?;
+                    seed = Some(parsed);
+                }
+                "--name" => options.player = value()?,
+                "--load" => options.load = Some(value()?),
+                "--save" => save = Some(Some(value()?)),
+                "--no-save" => save = Some(None),
+                "--binary" => options.format = SaveFormat::Binary,
+                _ => return Err(format!("Unknown option: {arg}")),
+            }
+        }
+
+        options.save = match save {
+            Some(save) => save,
+            None => options.load.clone().or(options.save),
+        };
+        if let Some(file) = &config {
+            options.config =
+                Config::load(file).map_err(|e| format!("Cannot load the config {file}: {e}"))?;
+        }
+        options.config.game_seed = match (seed, &config) {
+            (Some(seed), _) => seed,
+            (None, Some(_)) => options.config.game_seed,
+            (None, None) => rand::random(),
+        };
+        if let Err(errors) = options.config.validate() {
+            let errors = errors.iter().map(|e| format!("- {e}")).collect::<Vec<_>>();
+            return Err(format!("Invalid config:\n{}", errors.join("\n")));
+        }
+        Ok(options)
     }
 }
 
@@ -99,86 +338,182 @@ pub fn box_of(
 // https://misc.flogisoft.com/bash/tip_colors_and_formatting
 // https://gist.github.com/JBlond/2fea43a3049b38287e5e9cefc87b2124
 const COLOR_RESET: &str = "\x1b[0m";
-const COLOR_EFFECT: &str = "\x1b[95m";
+const COLOR_EXPLORED: &str = "\x1b[2m";
+const COLOR_ITEM: &str = "\x1b[93m";
 const COLOR_ENEMY: &str = "\x1b[38;5;1m";
 const COLOR_PLAYER: &str = "\x1b[38;5;208m";
 const COLOR_PLAYER_HEALTH: &str = "\x1b[31m";
 
+/// Numero di messaggi mostrati sotto la mappa nell'interfaccia console.
+const MESSAGES_SHOWN: usize = 5;
+
 /// Implementazione di una possibile interfaccia console.\
-/// Ha fin troppi metodi per far vedere in modo carino il gioco, ma comunque la parte importante
-/// è l'implementazione del Behavior.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct ConsoleInput;
+/// Il gioco viene mostrato a schermo intero tramite Screen (vedi il modulo tui), che ad ogni
+/// turno ridisegna solamente le parti cambiate e si adatta alla dimensione del terminale.\
+/// Lo schermo non viene serializzato, quindi dopo il caricamento di una partita il primo
+/// turno ridisegna tutto il terminale.\
+/// Insieme allo schermo viene ricordata la LookMap dell'ultimo frame, che permette di
+/// ispezionare le celle della mappa tramite la modalità look.\
+/// Lo stato del terminale è condiviso tra le copie dell'interfaccia, in modo che chi fa giocare
+/// la partita possa chiedere l'azione al giocatore prima del turno (vedi prepare_turn).\
+/// Quando il giocatore chiede di uscire salvando la partita viene impostato *save_request*,
+/// che viene controllato prima di calcolare il turno (vedi play_console_turn).
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConsoleInput {
+    #[serde(skip)]
+    terminal: Arc<Mutex<ConsoleTerminal>>,
+    #[serde(skip)]
+    save_request: Arc<AtomicBool>,
+}
 impl ConsoleInput {
-    /// Stampa il piano passato in input.\
-    /// Verranno usate altre funzioni di appoggio per formattare al meglio gli oggetti passati.
-    fn print_floor(&self, floor: FloorView, other: String) {
-        let mut term = console::Term::stdout();
-        let _ = term.clear_screen();
-        let _ = term.write_fmt(format_args!(
-            "{}{}\n{other}",
-            Self::floor_as_string(&floor),
-            Self::entity_as_string(floor.entity),
-        ));
-    }
-    /// Permette di prendere una stringa con le informazioni dell'entità.\
-    /// Alcune di esse sono il nome, la vita massima e quanto ne rimane sottoforma di HP bar.
-    fn entity_as_string(entity: &Entity) -> String {
-        let times = 20;
-        let health_bar = (entity.get_health() * times) / entity.get_health_max();
-
-        let filled = "■".repeat(health_bar as usize);
-        let empty = " ".repeat((times - health_bar) as usize);
-        format!(
-            "{}: [{COLOR_PLAYER_HEALTH}{filled}{empty}{COLOR_RESET}] {:4}/{:4}",
-            entity.get_name(),
-            entity.get_health(),
-            entity.get_health_max()
-        )
-    }
-    /// Permette di prendere una stringa con le informazioni del piano.\
-    /// Il risultato sarà una vista del piano con raggio 5 (scelto arbitrariamente),
-    /// e che quindi restituirà una porzione di campo 10x10 evidenziando eventuali celle o entità.
-    fn floor_as_string(floor: &FloorView) -> String {
-        let view = 5;
-        let size = (2 * view) * 3;
-        let iter = floor.get_grid(view).map(|iter| {
-            iter.map(|view| {
-                if let Some(e) = view.entity {
-                    let color = if floor.entity.position == e.position {
-                        COLOR_PLAYER
-                    } else {
-                        COLOR_ENEMY
-                    };
-                    return format!("{} {} {COLOR_RESET}", color, e.direction.as_char());
-                }
-
-                let cell = view.cell.as_char();
-                match view.cell {
-                    Cell::Special(_) => format!("{COLOR_EFFECT} {cell} {COLOR_RESET}"),
-                    Cell::Wall => format!("{cell}{cell}{cell}"),
-                    _ => format!(" {cell} "),
-                }
-            })
+    /// Crea una nuova interfaccia console.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Restituisce la richiesta impostata quando il giocatore chiede di uscire salvando la partita.
+    pub fn get_save_request(&self) -> &AtomicBool {
+        &self.save_request
+    }
+    /// Mostra al giocatore indicato il suo piano e gli chiede l'azione da fare nel prossimo turno,
+    /// prima che esso venga calcolato.\
+    /// In questo modo, se il giocatore chiede di uscire salvando, la partita può essere salvata
+    /// esattamente come la vede; altrimenti l'azione scelta viene usata durante il turno.\
+    /// Se il giocatore non può agire in questo turno (ad esempio perchè stordito) viene solamente
+    /// mostrato il piano.
+    pub fn prepare_turn(&self, game: &Dungeon, name: &str) {
+        let (Some(level), Some(player)) = (game.get_player_level(name), game.get_player(name))
+        else {
+            return;
+        };
+        let floor = game.get_floor(level);
+        let mut terminal = self.terminal.lock().unwrap();
+        terminal.print_floor(floor.get_limited_view_floor(player), None);
+        terminal.pending = None;
+        if player.get_actions() > 0 {
+            let action = terminal.read_action(player, Some(&self.save_request));
+            terminal.pending = Some(action);
+        }
+    }
+    /// Permette di prendere le righe con il riassunto della partita dell'entità.\
+    /// Il riassunto contiene le statistiche raccolte durante la partita e il punteggio ottenuto,
+    /// e viene mostrato in una finestra con il risultato come titolo.
+    fn summary_lines(entity: &Entity) -> Vec<String> {
+        let size = 30;
+        let statistics = entity.get_statistics();
+        let lines = [
+            ("Level", entity.get_stats().level.to_string()),
+            ("Deepest floor", statistics.deepest_floor.to_string()),
+            ("Turns played", statistics.turns.to_string()),
+            ("Kills", statistics.kills.to_string()),
+            ("Damage taken", statistics.get_total_damage().to_string()),
+            ("Score", statistics.get_score().to_string()),
+        ];
+        lines
+            .into_iter()
+            .map(|(name, value)| format!("{name:<w$}{value:>10}", w = size - 10))
             .collect()
+    }
+    /// Permette di prendere una stringa con la classifica dei punteggi passati.\
+    /// Per ogni punteggio vengono mostrati la posizione, il nome del giocatore, il risultato
+    /// della partita, il piano più profondo raggiunto e il punteggio.
+    fn high_scores_as_string(scores: &[Score]) -> String {
+        let size = 40;
+        let iter = scores.iter().enumerate().map(|(i, score)| {
+            let name = score.name.chars().take(12).collect::<String>();
+            let outcome = format!("{:?}", score.outcome);
+            format!(
+                "{:>2}. {name:<12} {outcome:<8} lv.{:<3} {:>7}",
+                i + 1,
+                score.deepest_floor,
+                score.score
+            )
         });
-
-        let title = format!(" Floor lv.{:2} ", floor.floor.get_level());
-        box_of(size, title, iter).collect()
+        box_of(size, " High scores ".to_string(), iter).collect()
+    }
+    /// Trasforma il carattere numerico premuto nell'indice dell'oggetto dell'inventario.
+    fn item_index(ch: char) -> usize {
+        ch.to_digit(10).unwrap_or(1).saturating_sub(1) as usize
     }
 }
-#[typetag::serde]
-impl Behavior for ConsoleInput {
-    fn update(&mut self, floor: FloorView) {
-        self.print_floor(floor, "".to_string());
+
+/// Stato del terminale usato da ConsoleInput: lo schermo, la LookMap dell'ultimo frame e
+/// l'eventuale azione già scelta dal giocatore prima del turno.
+#[derive(Debug, Default)]
+struct ConsoleTerminal {
+    screen: Screen,
+    look: LookMap,
+    pending: Option<Option<Action>>,
+}
+impl ConsoleTerminal {
+    /// Disegna il piano passato in input, adattandolo alla dimensione attuale del terminale.\
+    /// Nel caso in cui venga passata una finestra, essa viene disegnata sopra al piano.
+    fn print_floor(
+        &mut self,
+        floor: FloorView,
+        popup: Option<(String, Vec<String>, &'static str)>,
+    ) {
+        let (width, height) = Screen::size();
+        let height = height.saturating_sub(1);
+        let mut frame = tui::draw_view(&floor, width, height);
+        self.look = LookMap::new(&floor, width, height);
+        if let Some((title, lines, color)) = popup {
+            tui::draw_popup(&mut frame, &title, &lines, color);
+        }
+        self.screen.render(frame);
+        self.screen.prompt("");
     }
-    fn on_death(&mut self, floor: FloorView) {
-        self.print_floor(floor, format!("{}YOU DIED!{}\n", COLOR_ENEMY, COLOR_RESET));
+    /// Mostra una finestra sopra all'ultimo frame disegnato finchè non viene premuto un tasto,
+    /// dopodichè il frame precedente viene ripristinato.
+    fn show_popup(&mut self, title: &str, lines: &[String]) {
+        let Some(previous) = self.screen.get_last().cloned() else {
+            return;
+        };
+        let mut frame = previous.clone();
+        tui::draw_popup(&mut frame, title, lines, "");
+        self.screen.render(frame);
+        self.screen.prompt("Press ANY button to continue...");
+        let _ = console::Term::stdout().read_char();
+        self.screen.render(previous);
     }
-    fn get_next_action(&mut self, entity: &Entity) -> Option<Action> {
+    /// Modalità look, in cui il giocatore muove un cursore sulla mappa per ispezionare le celle.\
+    /// Il cursore parte dalla posizione indicata e la descrizione di quello che si trova sotto
+    /// di esso viene mostrata al posto dei messaggi; premendo un tasto diverso da [wasd] si esce.
+    fn look_mode(&mut self, start: Position) {
+        let Some(previous) = self.screen.get_last().cloned() else {
+            return;
+        };
+
+        let term = console::Term::stdout();
+        let mut cursor = start;
+        loop {
+            let mut frame = previous.clone();
+            tui::draw_look(&mut frame, &self.look, &cursor);
+            self.screen.render(frame);
+            self.screen
+                .prompt("Look mode [wasd to move the cursor, any other key to exit]: ");
+
+            let direction = match term.read_char() {
+                Ok('w') => Direction::Up,
+                Ok('a') => Direction::Left,
+                Ok('s') => Direction::Down,
+                Ok('d') => Direction::Right,
+                _ => break,
+            };
+            cursor = self.look.step(cursor, direction);
+        }
+        self.screen.render(previous);
+    }
+    /// Chiede al giocatore la prossima azione finchè non ne sceglie una valida.\
+    /// L'uscita salvando la partita è possibile solo se viene passato *save_request*, ovvero
+    /// prima che il turno venga calcolato; altrimenti il giocatore può solo uscire senza salvare.
+    fn read_action(
+        &mut self,
+        entity: &Entity,
+        save_request: Option<&AtomicBool>,
+    ) -> Option<Action> {
         let prompt = "Insert your action [? for help]: ";
-        let mut term = console::Term::stdout();
-        let _ = term.write(prompt.as_bytes());
+        let term = console::Term::stdout();
+        self.screen.prompt(prompt);
 
         loop {
             if let Ok(ch) = term.read_char() {
@@ -189,19 +524,36 @@ impl Behavior for ConsoleInput {
                     'a' => return Some(Action::Move(Direction::Left)),
                     's' => return Some(Action::Move(Direction::Down)),
                     'd' => return Some(Action::Move(Direction::Right)),
+                    'e' => return Some(Action::PickUp),
+                    '1'..='9' => return Some(Action::Use(ConsoleInput::item_index(ch))),
+                    'r' => {
+                        if let Ok(ch @ '1'..='9') = term.read_char() {
+                            return Some(Action::Drop(ConsoleInput::item_index(ch)));
+                        }
+                    }
                     'q' => {
-                        let _ = term.write_line("");
-                        return None;
+                        self.screen.prompt(match save_request {
+                            Some(_) => {
+                                "Save the game before quitting? [y/n, any other key to cancel] "
+                            }
+                            None => "Quit without saving? [y, any other key to cancel] ",
+                        });
+                        match (term.read_char(), save_request) {
+                            (Ok('y'), Some(save_request)) => {
+                                save_request.store(true, Ordering::Relaxed);
+                                return Some(Action::DoNothing);
+                            }
+                            (Ok('n'), Some(_)) | (Ok('y'), None) => return None,
+                            _ => self.screen.prompt(prompt),
+                        }
+                    }
+                    'l' => {
+                        self.look_mode(entity.position);
+                        self.screen.prompt(prompt);
                     }
                     '?' => {
-                        let _ = term.write_line("");
-                        let (message, lines) = get_help_message();
-                        let _ = term.write(message.as_bytes());
-                        let _ = term.read_char(); // waiting for user acknowledgment
-                        let _ = term.clear_line(); // clear line "press button..."
-                        let _ = term.clear_last_lines(lines); // this number is from the message
-                        let _ = term.move_cursor_up(1); // moving up since the first write_line put me down by one
-                        let _ = term.move_cursor_right(prompt.len()); // moving at the end of the prompt
+                        self.show_popup(" Help ", &get_help_message());
+                        self.screen.prompt(prompt);
                     }
                     _ => (),
                 }
@@ -209,27 +561,53 @@ impl Behavior for ConsoleInput {
         }
     }
 }
+#[typetag::serde]
+impl Behavior for ConsoleInput {
+    fn update(&mut self, floor: FloorView, _rng: &mut Pcg32) {
+        self.terminal.lock().unwrap().print_floor(floor, None);
+    }
+    fn on_death(&mut self, floor: FloorView) {
+        let summary = Self::summary_lines(floor.entity);
+        self.terminal.lock().unwrap().print_floor(
+            floor,
+            Some((" YOU DIED! ".to_string(), summary, COLOR_ENEMY)),
+        );
+    }
+    fn on_victory(&mut self, floor: FloorView) {
+        let summary = Self::summary_lines(floor.entity);
+        self.terminal.lock().unwrap().print_floor(
+            floor,
+            Some((" YOU WIN! ".to_string(), summary, COLOR_PLAYER)),
+        );
+    }
+    fn get_next_action(&mut self, entity: &Entity) -> Option<Action> {
+        let mut terminal = self.terminal.lock().unwrap();
+        match terminal.pending.take() {
+            Some(action) => action,
+            None => terminal.read_action(entity, None),
+        }
+    }
+}
 
-fn get_help_message() -> (String, usize) {
-    let help_message = vec![
+fn get_help_message() -> Vec<String> {
+    vec![
         format!(
-            "{}Objective{}: survive and reach the next floor through {}",
-            COLOR_PLAYER_HEALTH,
-            COLOR_RESET,
+            "Objective: survive and reach the next floor through {}",
             Cell::Exit.as_char()
         ),
-        format!(
-            "Special effect cell are colored with {}█{}",
-            COLOR_EFFECT, COLOR_RESET
-        ),
+        "You win when reaching the floor, turns or score set in the configuration".to_string(),
+        "On boss floors the exit stays closed until the boss is defeated".to_string(),
+        "Each special effect has its own symbol and color, items are yellow".to_string(),
+        "The side panel shows your stats, effects and a map of the floor".to_string(),
+        String::new(),
         "[wasd]  => for movement".to_string(),
         "[space] => for attacking the enemy in front".to_string(),
+        "[e]     => for picking up the item below".to_string(),
+        "[1-9]   => for using the item in the inventory".to_string(),
+        "[r 1-9] => for dropping the item in the inventory".to_string(),
         "[z]     => for doing nothing".to_string(),
-        "[q]     => for exit the game".to_string(),
-        "Press ANY button to continue...".to_string(),
-    ];
-
-    let count = help_message.len() - 1; // the last one doesn't have the newline
-    let string = help_message.join("\n");
-    (string, count)
+        "[l]     => for looking at the cells of the map".to_string(),
+        "[q]     => for exit the game, saving it if you want".to_string(),
+        "The game is also saved every time you change floor".to_string(),
+    ]
 }
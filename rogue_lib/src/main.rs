@@ -1,4 +1,16 @@
+use rogue_lib::{ConsoleOptions, CONSOLE_USAGE};
+
 fn main() {
-    let seed = rand::random();
-    rogue_lib::run_console("Player".to_string(), seed);
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        return println!("{CONSOLE_USAGE}");
+    }
+
+    match ConsoleOptions::from_args(args) {
+        Ok(options) => rogue_lib::run_console(options),
+        Err(e) => {
+            eprintln!("{e}\n\n{CONSOLE_USAGE}");
+            std::process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,95 @@
+use super::{cell::Effect, item::Item};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader},
+};
+
+/// Carattere che indica un muro all'interno dello schema di una stanza prefabbricata.
+pub const PREFAB_WALL: char = '#';
+
+/// Contenuto di una cella di una stanza prefabbricata.\
+/// *Spawn* indica un punto in cui viene generata l'entità della configurazione con il nome indicato,
+/// che può essere anche un boss o uno dei suoi seguaci (ad esempio per l'arena di un boss);
+/// Config::validate segnala i nomi che non corrispondono a nessuna entità.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum PrefabTile {
+    Wall,
+    Empty,
+    Effect(Box<dyn Effect>),
+    Item(Box<dyn Item>),
+    Spawn(String),
+}
+
+/// Stanza prefabbricata, disegnata a mano, che il generatore può inserire nel piano
+/// insieme alle stanze casuali (ad esempio una stanza del tesoro o l'arena di un boss).\
+/// Lo schema è formato da righe di testo, dove ogni carattere rappresenta una cella:
+/// la prima riga è quella più in alto nel piano e il primo carattere è quello più a sinistra.\
+/// I caratteri presenti nella *legend* indicano il contenuto della cella, altrimenti il carattere
+/// PREFAB_WALL indica un muro e tutti gli altri (ad esempio '.') indicano una cella vuota.\
+/// \
+/// Dato che la stanza viene collegata al resto del piano tramite delle porte sui suoi lati,
+/// è consigliato lasciare vuoto il bordo dello schema, altrimenti alcune porte potrebbero non
+/// portare da nessuna parte.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Prefab {
+    pub name: String,
+    pub layout: Vec<String>,
+    #[serde(default)]
+    pub legend: BTreeMap<char, PrefabTile>,
+}
+
+impl Prefab {
+    /// Crea una stanza a partire dal testo dello schema, senza nessun elemento nella legenda.\
+    /// Ogni riga del testo diventa una riga dello schema.
+    pub fn new(name: String, layout: &str) -> Self {
+        Self {
+            name,
+            layout: layout.lines().map(str::to_string).collect(),
+            legend: BTreeMap::new(),
+        }
+    }
+
+    /// Carica la stanza da un file.\
+    /// Il file deve essere formattato tramite json, altrimenti viene ritornato un errore.
+    pub fn load(filename: &str) -> io::Result<Self> {
+        let file = File::open(filename)?;
+        let reader = BufReader::new(file);
+        let prefab: Self = serde_json::from_reader(reader)?;
+        Ok(prefab)
+    }
+
+    /// Restituisce la larghezza della stanza, ovvero la lunghezza della riga più lunga.
+    pub fn get_width(&self) -> usize {
+        self.layout
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Restituisce l'altezza della stanza, ovvero il numero di righe dello schema.
+    pub fn get_height(&self) -> usize {
+        self.layout.len()
+    }
+
+    /// Ritorna un iteratore a tutte le celle dello schema insieme alla loro colonna e riga.\
+    /// Le righe più corte della larghezza della stanza vengono considerate vuote alla fine.
+    pub fn get_tiles(&self) -> impl Iterator<Item = (usize, usize, PrefabTile)> + '_ {
+        self.layout.iter().enumerate().flat_map(move |(row, line)| {
+            line.chars()
+                .enumerate()
+                .map(move |(col, c)| (col, row, self.get_tile(c)))
+        })
+    }
+
+    /// Restituisce il contenuto della cella indicata dal carattere passato.
+    fn get_tile(&self, c: char) -> PrefabTile {
+        match self.legend.get(&c) {
+            Some(tile) => tile.clone(),
+            None if c == PREFAB_WALL => PrefabTile::Wall,
+            None => PrefabTile::Empty,
+        }
+    }
+}
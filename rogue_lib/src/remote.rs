@@ -0,0 +1,314 @@
+use super::{
+    config::Config,
+    entities::{Action, Behavior, Entity},
+    floor::{FloorView, SIGHT_RADIUS},
+    game::Dungeon,
+    snapshot::FloorSnapshot,
+};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    io::{self, ErrorKind, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Lunghezza massima in byte di un singolo messaggio.\
+/// Una riga più lunga viene considerata un messaggio non valido, in modo che un client
+/// non possa far crescere all'infinito la memoria usata dal server.
+pub const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+/// Messaggio inviato dal server ad un client remoto.\
+/// Il protocollo è formato da un messaggio JSON per ogni riga.\
+/// *View* viene inviato ogni volta che viene chiesta un'azione al giocatore, insieme al numero
+/// della richiesta, che cresce ad ogni vista e che il client deve restituire con l'azione.\
+/// *GameOver* viene inviato quando il giocatore muore, con l'ultima vista del piano.\
+/// *Victory* viene inviato quando il giocatore vince la partita, con l'ultima vista del piano.\
+/// *Rejected* viene inviato quando il server rifiuta il Join, con il motivo, prima di chiudere la connessione.\
+/// Le viste sono delle FloorSnapshot con raggio pari a quello di visione delle entità.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ServerMessage {
+    View(u64, FloorSnapshot),
+    GameOver(FloorSnapshot),
+    Victory(FloorSnapshot),
+    Rejected(String),
+}
+
+/// Messaggio inviato da un client remoto al server.\
+/// Il protocollo è formato da un messaggio JSON per ogni riga.\
+/// Il primo messaggio deve essere *Join* con il nome del giocatore, mentre tutti i
+/// successivi devono essere *Action* con il numero della vista a cui rispondono e l'azione da fare.\
+/// Le azioni che rispondono ad una vista vecchia (ad esempio arrivate dopo il timeout) vengono scartate.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ClientMessage {
+    Join(String),
+    Action(u64, Action),
+}
+
+/// Connessione con un client remoto.\
+/// Contiene lo stream per la lettura, quello per la scrittura e i byte già letti che
+/// non formano ancora una riga completa, in modo che non vengano persi nel caso in cui
+/// il timeout scatti a metà di un messaggio.
+#[derive(Debug)]
+struct Connection {
+    reader: TcpStream,
+    writer: TcpStream,
+    buffer: Vec<u8>,
+}
+
+impl Connection {
+    /// Crea una nuova connessione a partire dallo stream indicato.
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            reader: stream.try_clone()?,
+            writer: stream,
+            buffer: vec![],
+        })
+    }
+
+    /// Invia il messaggio come una singola riga JSON.
+    fn send<T: Serialize>(&mut self, message: &T) -> io::Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.flush()
+    }
+
+    /// Legge un messaggio da una singola riga JSON.\
+    /// Nel caso in cui la connessione sia stata chiusa viene ritornato un errore UnexpectedEof,
+    /// mentre se la riga supera MAX_MESSAGE_SIZE viene ritornato un errore InvalidData.\
+    /// Se la lettura viene interrotta dal timeout, i byte già letti vengono tenuti per la prossima chiamata.
+    fn receive<T: for<'de> Deserialize<'de>>(&mut self) -> io::Result<T> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let line = self.buffer.drain(..=end).collect::<Vec<_>>();
+                return Ok(serde_json::from_slice(&line)?);
+            }
+            if self.buffer.len() > MAX_MESSAGE_SIZE {
+                return Err(io::Error::new(ErrorKind::InvalidData, "Message too long"));
+            }
+
+            let mut chunk = [0; 4096];
+            let read = self.reader.read(&mut chunk)?;
+            if read == 0 {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+/// Comportamento di un giocatore controllato da un client remoto tramite TCP.\
+/// Ad ogni turno viene inviata al client la vista del piano e viene letta l'azione da fare.\
+/// Nel caso in cui il client non risponda entro il timeout dello stream, il giocatore
+/// non fa nulla per il turno corrente e la risposta in ritardo verrà scartata; se invece
+/// la connessione viene chiusa o il client invia un messaggio non valido, il giocatore
+/// viene rimosso dal gioco.\
+/// La connessione non viene serializzata, quindi un giocatore remoto caricato da un file
+/// verrà rimosso al primo turno.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RemoteBehavior {
+    #[serde(skip)]
+    connection: Option<Arc<Mutex<Connection>>>,
+    #[serde(skip)]
+    request: u64,
+}
+
+impl RemoteBehavior {
+    /// Crea un comportamento remoto a partire dallo stream di un client già connesso.
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        let connection = Connection::new(stream)?;
+        Ok(Self {
+            connection: Some(Arc::new(Mutex::new(connection))),
+            request: 0,
+        })
+    }
+
+    /// Accetta un nuovo client dal listener indicato e legge il suo messaggio di Join.\
+    /// Viene restituito il nome del giocatore e il comportamento da usare per esso.
+    pub fn accept(listener: &TcpListener) -> io::Result<(String, Self)> {
+        let (stream, _) = listener.accept()?;
+        Self::join(stream)
+    }
+
+    /// Legge il messaggio di Join dal client già connesso tramite lo stream indicato.\
+    /// Viene restituito il nome del giocatore e il comportamento da usare per esso.
+    pub fn join(stream: TcpStream) -> io::Result<(String, Self)> {
+        let behavior = Self::new(stream)?;
+        let message = behavior.with_connection(|connection| connection.receive())?;
+        match message {
+            ClientMessage::Join(name) => Ok((name, behavior)),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Expected a Join message",
+            )),
+        }
+    }
+
+    /// Rifiuta il client inviandogli il motivo e chiudendo la connessione.
+    pub fn reject(&self, reason: String) {
+        let _ = self.with_connection(|c| c.send(&ServerMessage::Rejected(reason)));
+        self.disconnect();
+    }
+
+    /// Chiude la connessione con il client, che riceverà la fine dello stream.\
+    /// Dato che la connessione è condivisa tra le copie del comportamento, anche esse
+    /// non potranno più comunicare con il client.
+    pub fn disconnect(&self) {
+        let _ = self.with_connection(|c| c.writer.shutdown(Shutdown::Both));
+    }
+
+    /// Esegue la funzione passata sulla connessione, se esiste.
+    fn with_connection<T>(
+        &self,
+        function: impl FnOnce(&mut Connection) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let connection = self.connection.as_ref().ok_or(ErrorKind::NotConnected)?;
+        let mut connection = connection.lock().map_err(|_| ErrorKind::BrokenPipe)?;
+        function(&mut connection)
+    }
+
+    /// Invia il messaggio al client; in caso di errore la connessione viene chiusa.
+    fn send(&mut self, message: ServerMessage) {
+        if self.with_connection(|c| c.send(&message)).is_err() {
+            self.connection = None;
+        }
+    }
+}
+
+#[typetag::serde]
+impl Behavior for RemoteBehavior {
+    fn update(&mut self, view: FloorView, _rng: &mut Pcg32) {
+        self.request += 1;
+        self.send(ServerMessage::View(
+            self.request,
+            view.snapshot(SIGHT_RADIUS),
+        ));
+    }
+    fn on_death(&mut self, view: FloorView) {
+        self.send(ServerMessage::GameOver(view.snapshot(SIGHT_RADIUS)));
+    }
+    fn on_victory(&mut self, view: FloorView) {
+        self.send(ServerMessage::Victory(view.snapshot(SIGHT_RADIUS)));
+    }
+    fn get_next_action(&mut self, _entity: &Entity) -> Option<Action> {
+        loop {
+            match self.with_connection(|c| c.receive()) {
+                Ok(ClientMessage::Action(request, action)) if request == self.request => {
+                    return Some(action)
+                }
+                Ok(ClientMessage::Action(request, _)) if request < self.request => continue,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Some(Action::DoNothing)
+                }
+                _ => {
+                    self.connection = None;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Client che permette di giocare ad un dungeon ospitato da un server remoto.\
+/// Utile per creare dei bot o delle interfacce che giocano tramite la rete.
+#[derive(Debug)]
+pub struct RemoteClient {
+    connection: Connection,
+    request: u64,
+}
+
+impl RemoteClient {
+    /// Si connette al server indicato ed entra nel gioco con il nome passato.
+    pub fn connect(address: impl ToSocketAddrs, name: String) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        let mut connection = Connection::new(stream)?;
+        connection.send(&ClientMessage::Join(name))?;
+        Ok(Self {
+            connection,
+            request: 0,
+        })
+    }
+
+    /// Aspetta il prossimo messaggio del server.\
+    /// Nel caso in cui il server chiuda la connessione viene ritornato un errore UnexpectedEof.
+    pub fn receive(&mut self) -> io::Result<ServerMessage> {
+        let message = self.connection.receive()?;
+        if let ServerMessage::View(request, _) = &message {
+            self.request = *request;
+        }
+        Ok(message)
+    }
+
+    /// Invia al server l'azione da fare in risposta all'ultima vista ricevuta.
+    pub fn send_action(&mut self, action: Action) -> io::Result<()> {
+        let message = ClientMessage::Action(self.request, action);
+        self.connection.send(&message)
+    }
+}
+
+/// Server che ospita una partita a cui partecipano dei client remoti.\
+/// *players* indica quanti giocatori devono connettersi prima di iniziare la partita.\
+/// *max_turns* indica il numero massimo di turni dopo cui la partita viene interrotta.\
+/// *timeout* indica quanto tempo ha un client per rispondere con un'azione, dopo di che
+/// il suo giocatore non fa nulla per il turno; se None il server aspetta per sempre.
+#[derive(Clone, Debug)]
+pub struct Server {
+    pub config: Config,
+    pub players: usize,
+    pub max_turns: usize,
+    pub timeout: Option<Duration>,
+}
+
+impl Server {
+    /// Crea un server per un solo giocatore, senza timeout e con al massimo 1000 turni.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            players: 1,
+            max_turns: 1000,
+            timeout: None,
+        }
+    }
+
+    /// Ospita una partita accettando i client dal listener indicato.\
+    /// Vengono accettati esattamente il numero di giocatori indicato, dopodichè la partita
+    /// viene giocata finchè ci sono giocatori in gioco o fino al numero massimo di turni.\
+    /// I client che non completano il Join o che usano un nome già preso vengono scartati
+    /// senza fermare il server, che continua ad aspettare altri giocatori.\
+    /// Alla fine tutte le connessioni vengono chiuse e viene restituito il dungeon,
+    /// in modo da poter vedere com'è andata la partita.
+    pub fn run(&self, listener: &TcpListener) -> io::Result<Dungeon> {
+        let mut dungeon = Dungeon::new_with(self.config.clone());
+        let mut behaviors = vec![];
+        let mut names = HashSet::new();
+        while behaviors.len() < self.players {
+            let (stream, address) = listener.accept()?;
+            let joined = stream
+                .set_read_timeout(self.timeout)
+                .and_then(|_| RemoteBehavior::join(stream));
+            match joined {
+                Ok((name, behavior)) if names.contains(&name) => {
+                    eprintln!("Rejected {address}: the name {name} is already taken");
+                    behavior.reject(format!("The name {name} is already taken"));
+                }
+                Ok((name, behavior)) => {
+                    names.insert(name.clone());
+                    dungeon.add_player(name, Box::new(behavior.clone()));
+                    behaviors.push(behavior);
+                }
+                Err(e) => eprintln!("Rejected {address}: {e}"),
+            }
+        }
+
+        while dungeon.has_players() && dungeon.get_turn() < self.max_turns {
+            dungeon.compute_turn();
+        }
+        for behavior in behaviors {
+            behavior.disconnect();
+        }
+        Ok(dungeon)
+    }
+}
@@ -0,0 +1,163 @@
+use super::{
+    config::Config,
+    entities::{Action, Behavior, Entity},
+    game::Dungeon,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, BufWriter},
+};
+
+/// Registrazione di una partita.\
+/// Dato che il dungeon è generato a partire dal seed della configurazione, per poter
+/// riprodurre una partita basta salvare la configurazione e le azioni che i giocatori
+/// hanno fatto in ogni turno.\
+/// Per ogni turno viene salvato anche un checksum dello stato del dungeon, in modo da
+/// poter controllare che la riproduzione sia identica alla partita originale.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Replay {
+    config: Config,
+    players: Vec<String>,
+    turns: Vec<ReplayTurn>,
+}
+
+/// Singolo turno di una registrazione.\
+/// Contiene le azioni dei giocatori (identificati dal nome) e il checksum
+/// del dungeon alla fine del turno.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReplayTurn {
+    actions: Vec<(String, Action)>,
+    checksum: u64,
+}
+
+/// Errore che indica che la riproduzione di una partita non è andata come quella registrata.\
+/// Il turno indicato è il primo in cui lo stato del dungeon è risultato diverso.\
+/// Questo può succedere nel caso in cui ci siano dei comportamenti che usano dei
+/// numeri casuali non derivati dal seed della partita.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    pub turn: usize,
+    pub expected: u64,
+    pub found: u64,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Replay diverged at turn {}: expected checksum {:x}, found {:x}",
+            self.turn, self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+impl Replay {
+    /// Crea una nuova registrazione vuota per una partita con la configurazione indicata.
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            players: vec![],
+            turns: vec![],
+        }
+    }
+
+    /// Carica la registrazione da un file.\
+    /// Il file deve essere formattato tramite json, altrimenti viene ritornato un errore.
+    pub fn load(filename: &str) -> io::Result<Self> {
+        let file = File::open(filename)?;
+        let reader = BufReader::new(file);
+        let replay: Self = serde_json::from_reader(reader)?;
+        Ok(replay)
+    }
+
+    /// Salva la registrazione nel file indicato tramite serializzazione JSON.\
+    /// Nel caso in cui ci siano problemi con I/O, viene ritornato un errore.
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        let file = File::create(filename)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Aggiunge un giocatore alla registrazione.\
+    /// I giocatori vengono identificati tramite il loro nome, quindi esso deve essere unico.
+    pub fn add_player(&mut self, name: String) {
+        assert!(
+            !self.players.contains(&name),
+            "Recorded players must have unique names!"
+        );
+        self.players.push(name);
+    }
+
+    /// Aggiunge un turno alla registrazione con le azioni dei giocatori e il checksum del dungeon.
+    pub fn add_turn(&mut self, actions: Vec<(String, Action)>, checksum: u64) {
+        self.turns.push(ReplayTurn { actions, checksum });
+    }
+
+    /// Restituisce il numero di turni registrati.
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    /// Indica se la registrazione non ha nessun turno.
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// Riproduce la partita registrata a partire dalla configurazione salvata.\
+    /// Ogni giocatore viene aggiunto con un ReplayBehavior che ripete le azioni registrate.\
+    /// Alla fine di ogni turno viene controllato il checksum del dungeon e nel caso in cui sia
+    /// diverso da quello registrato viene ritornato un errore che indica il turno della divergenza.\
+    /// Altrimenti viene ritornato il dungeon nello stato finale della partita.
+    pub fn play(&self) -> Result<Dungeon, Divergence> {
+        let mut dungeon = Dungeon::new_with(self.config.clone());
+        for name in &self.players {
+            let actions = self
+                .turns
+                .iter()
+                .flat_map(|turn| turn.actions.iter())
+                .filter(|(player, _)| player == name)
+                .map(|(_, action)| action.clone())
+                .collect();
+            dungeon.add_player(name.clone(), Box::new(ReplayBehavior::new(actions)));
+        }
+
+        for recorded in &self.turns {
+            dungeon.compute_turn();
+            let found = dungeon.checksum();
+            if found != recorded.checksum {
+                return Err(Divergence {
+                    turn: dungeon.get_turn(),
+                    expected: recorded.checksum,
+                    found,
+                });
+            }
+        }
+        Ok(dungeon)
+    }
+}
+
+/// Comportamento che ripete una sequenza di azioni registrate.\
+/// Una volta terminate le azioni, il behavior restituirà None e quindi l'entità
+/// verrà rimossa dal gioco, esattamente come se il giocatore fosse uscito.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayBehavior {
+    actions: VecDeque<Action>,
+}
+impl ReplayBehavior {
+    pub fn new(actions: Vec<Action>) -> Self {
+        let actions = VecDeque::from(actions);
+        Self { actions }
+    }
+}
+#[typetag::serde]
+impl Behavior for ReplayBehavior {
+    fn get_next_action(&mut self, _entity: &Entity) -> Option<Action> {
+        self.actions.pop_front()
+    }
+}
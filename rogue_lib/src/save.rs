@@ -0,0 +1,393 @@
+use super::{
+    entities::{CombatStats, Growth},
+    game::Dungeon,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{error::Category, Map, Value};
+use std::{
+    fmt::Display,
+    io::{self, Read, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Versione attuale del formato dei salvataggi.\
+/// Deve essere incrementata ogni volta che la struttura del dungeon cambia in modo non
+/// compatibile, aggiungendo in migrate la migrazione dalla versione precedente.\
+/// La versione 0 indica i salvataggi fatti prima dell'introduzione delle versioni, che
+/// contengono direttamente il dungeon senza nessuna intestazione.
+pub const SAVE_VERSION: u32 = 2;
+
+/// Byte iniziali che identificano un salvataggio in formato binario.\
+/// Dato che un salvataggio JSON inizia sempre con '{' o con degli spazi, il formato
+/// del file può essere riconosciuto leggendo solamente questi byte.
+pub const BINARY_MAGIC: &[u8] = b"ROGUESAV";
+
+/// Formato con cui viene scritto un salvataggio.\
+/// *Json* è il formato di base, facile da leggere e modificare a mano.\
+/// *Binary* è un formato compatto, in cui le righe dei piani vengono compresse tramite
+/// run-length encoding e il tutto viene serializzato tramite MessagePack.\
+/// Entrambi i formati contengono le stesse informazioni e vengono riconosciuti automaticamente
+/// durante il caricamento (vedi read).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SaveFormat {
+    #[default]
+    Json,
+    Binary,
+}
+
+/// Errore che può avvenire durante il salvataggio o il caricamento di un dungeon.\
+/// *Io* indica un problema con la lettura o la scrittura del file.\
+/// *Parse* indica che il file non è formattato correttamente o che non contiene un dungeon.\
+/// *Binary* indica che il file in formato binario è danneggiato o non contiene un dungeon.\
+/// *UnknownType* indica che nel file c'è un tipo (ad esempio un Effect o un Behavior) che
+/// non è conosciuto da questa versione del gioco.\
+/// *Version* indica che il file è stato salvato con un formato più recente di quello supportato.
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    Binary(String),
+    UnknownType(String),
+    Version { found: u32, supported: u32 },
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::Parse(e) => write!(f, "Invalid save file: {e}"),
+            Self::Binary(e) => write!(f, "Invalid binary save file: {e}"),
+            Self::UnknownType(e) => write!(f, "Unknown type in save file: {e}"),
+            Self::Version { found, supported } => write!(
+                f,
+                "Save format {found} is newer than the supported one ({supported})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        match e.classify() {
+            Category::Io => Self::Io(e.into()),
+            Category::Data if e.to_string().starts_with("unknown variant") => {
+                Self::UnknownType(e.to_string())
+            }
+            _ => Self::Parse(e),
+        }
+    }
+}
+
+impl From<rmp_serde::encode::Error> for SaveError {
+    fn from(e: rmp_serde::encode::Error) -> Self {
+        Self::Binary(e.to_string())
+    }
+}
+
+impl From<rmp_serde::decode::Error> for SaveError {
+    fn from(e: rmp_serde::decode::Error) -> Self {
+        Self::Binary(e.to_string())
+    }
+}
+
+/// Intestazione di un salvataggio, utile per sapere da dove proviene il dungeon salvato.\
+/// *format* indica la versione del formato del salvataggio (vedi SAVE_VERSION).\
+/// *crate_version* indica la versione della libreria con cui è stato fatto il salvataggio.\
+/// *seed* indica il seed del dungeon salvato.\
+/// *timestamp* indica il momento del salvataggio in secondi dal 1970 (UNIX epoch).
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SaveHeader {
+    pub format: u32,
+    pub crate_version: String,
+    pub seed: u64,
+    pub timestamp: u64,
+}
+
+impl SaveHeader {
+    /// Crea l'intestazione di un salvataggio fatto in questo momento con il formato attuale.
+    pub fn new(seed: u64) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        Self {
+            format: SAVE_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            timestamp,
+        }
+    }
+}
+
+/// Struttura di un file di salvataggio, formata dall'intestazione e dal dungeon.
+#[derive(Deserialize, Serialize)]
+struct SaveFile<T> {
+    header: SaveHeader,
+    dungeon: T,
+}
+
+/// Scrive il dungeon nel writer indicato nel formato scelto, insieme all'intestazione.
+pub fn write(
+    dungeon: &Dungeon,
+    format: SaveFormat,
+    mut writer: impl Write,
+) -> Result<(), SaveError> {
+    let header = SaveHeader::new(dungeon.get_config().game_seed);
+    match format {
+        SaveFormat::Json => {
+            let save = SaveFile { header, dungeon };
+            serde_json::to_writer_pretty(&mut writer, &save)?;
+        }
+        SaveFormat::Binary => {
+            let mut dungeon = serde_json::to_value(dungeon)?;
+            for_each_grid(&mut dungeon, encode_grid)?;
+            let save = SaveFile { header, dungeon };
+            writer.write_all(BINARY_MAGIC)?;
+            writer.write_all(&rmp_serde::to_vec_named(&save)?)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Legge un dungeon salvato tramite write dal reader indicato.\
+/// Il formato del salvataggio viene riconosciuto automaticamente.\
+/// Nel caso in cui il salvataggio sia stato fatto con un formato precedente, il dungeon viene
+/// migrato al formato attuale prima di essere caricato.
+pub fn read(reader: impl Read) -> Result<Dungeon, SaveError> {
+    let value = read_value(reader)?;
+    let (header, mut dungeon) = split_header(value)?;
+    if header.format > SAVE_VERSION {
+        return Err(SaveError::Version {
+            found: header.format,
+            supported: SAVE_VERSION,
+        });
+    }
+
+    for version in header.format..SAVE_VERSION {
+        migrate(version, &mut dungeon);
+    }
+    Ok(serde_json::from_value(dungeon)?)
+}
+
+/// Legge solamente l'intestazione di un salvataggio dal reader indicato.\
+/// Per i salvataggi senza intestazione (formato 0) viene restituita un'intestazione con
+/// il seed della configurazione salvata, versione della libreria sconosciuta e timestamp 0.
+pub fn read_header(reader: impl Read) -> Result<SaveHeader, SaveError> {
+    let value = read_value(reader)?;
+    Ok(split_header(value)?.0)
+}
+
+/// Restituisce il formato del salvataggio contenuto nei byte indicati.
+pub fn detect_format(bytes: &[u8]) -> SaveFormat {
+    if bytes.starts_with(BINARY_MAGIC) {
+        SaveFormat::Binary
+    } else {
+        SaveFormat::Json
+    }
+}
+
+/// Legge il salvataggio dal reader e lo trasforma nella sua rappresentazione JSON.\
+/// Per i salvataggi binari i piani vengono decompressi, in modo che il risultato sia
+/// uguale a quello di un salvataggio JSON.
+fn read_value(mut reader: impl Read) -> Result<Value, SaveError> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    match detect_format(&bytes) {
+        SaveFormat::Json => Ok(serde_json::from_slice(&bytes)?),
+        SaveFormat::Binary => {
+            let mut save: SaveFile<Value> = rmp_serde::from_slice(&bytes[BINARY_MAGIC.len()..])?;
+            for_each_grid(&mut save.dungeon, decode_grid)?;
+            Ok(serde_json::to_value(save)?)
+        }
+    }
+}
+
+/// Applica la funzione passata alla griglia di ogni piano del dungeon.\
+/// Si ferma al primo errore ritornato dalla funzione.
+fn for_each_grid(
+    dungeon: &mut Value,
+    function: fn(&mut Value) -> Result<(), SaveError>,
+) -> Result<(), SaveError> {
+    let floors = dungeon.get_mut("floors").and_then(Value::as_array_mut);
+    for floor in floors.into_iter().flatten() {
+        if let Some(grid) = floor.get_mut("grid") {
+            function(grid)?;
+        }
+    }
+    Ok(())
+}
+
+/// Comprime ogni riga della griglia tramite run-length encoding.\
+/// Ogni riga diventa una lista di coppie [ripetizioni, cella], dove le celle consecutive
+/// uguali vengono unite in una sola coppia.
+fn encode_grid(grid: &mut Value) -> Result<(), SaveError> {
+    for row in grid.as_array_mut().into_iter().flatten() {
+        let mut runs: Vec<(u64, Value)> = vec![];
+        for cell in row.as_array_mut().into_iter().flatten() {
+            match runs.last_mut() {
+                Some((count, last)) if last == cell => *count += 1,
+                _ => runs.push((1, cell.take())),
+            }
+        }
+        *row = runs
+            .into_iter()
+            .map(|(count, cell)| Value::Array(vec![count.into(), cell]))
+            .collect();
+    }
+    Ok(())
+}
+
+/// Decomprime ogni riga della griglia compressa tramite encode_grid.\
+/// Dato che la griglia è quadrata, ogni riga decompressa deve avere tante celle quante sono
+/// le righe; nel caso in cui una coppia sia malformata o una riga abbia una lunghezza diversa,
+/// viene ritornato un errore senza allocare le celle in eccesso.
+fn decode_grid(grid: &mut Value) -> Result<(), SaveError> {
+    let error = |problem: &str| Err(SaveError::Binary(format!("Invalid grid: {problem}")));
+    let Some(rows) = grid.as_array_mut() else {
+        return error("expected a list of rows");
+    };
+
+    let size = rows.len();
+    for row in rows.iter_mut() {
+        let Some(runs) = row.as_array() else {
+            return error("expected a list of runs");
+        };
+        let mut cells = Vec::with_capacity(size);
+        for run in runs {
+            let Some([count, cell]) = run.as_array().map(Vec::as_slice) else {
+                return error("expected a [count, cell] pair");
+            };
+            let count = match count.as_u64().and_then(|n| usize::try_from(n).ok()) {
+                Some(count) if count > 0 && count <= size - cells.len() => count,
+                _ => return error(&format!("invalid run length {count}")),
+            };
+            cells.extend(std::iter::repeat_n(cell.clone(), count));
+        }
+        if cells.len() != size {
+            return error(&format!("row of {} cells instead of {size}", cells.len()));
+        }
+        *row = Value::Array(cells);
+    }
+    Ok(())
+}
+
+/// Divide il salvataggio nella sua intestazione e nel dungeon salvato.
+fn split_header(value: Value) -> Result<(SaveHeader, Value), SaveError> {
+    if value.get("header").is_some() {
+        let save: SaveFile<Value> = serde_json::from_value(value)?;
+        return Ok((save.header, save.dungeon));
+    }
+
+    let seed = value
+        .pointer("/config/game_seed")
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+    let header = SaveHeader {
+        format: 0,
+        crate_version: "unknown".to_string(),
+        seed,
+        timestamp: 0,
+    };
+    Ok((header, value))
+}
+
+/// Migra il dungeon salvato dalla versione indicata a quella successiva.\
+/// Le migrazioni lavorano direttamente sulla rappresentazione JSON, in modo da poter
+/// modificare campi che non esistono più nelle strutture attuali.
+fn migrate(version: u32, dungeon: &mut Value) {
+    match version {
+        0 => migrate_legacy(dungeon),
+        1 => migrate_boss(dungeon),
+        _ => unreachable!("Missing migration from save format {version}"),
+    }
+}
+
+/// Migrazione dei salvataggi senza intestazione.\
+/// In essi le entità avevano solamente l'attacco al posto delle statistiche di combattimento,
+/// e nella configurazione mancavano gli oggetti e le statistiche di difesa, precisione e crescita.
+fn migrate_legacy(dungeon: &mut Value) {
+    let growth = serde_json::to_value(Growth::default()).unwrap_or_default();
+    let defaults = |object: &mut Map<String, Value>| {
+        object.entry("defense").or_insert(0.into());
+        object.entry("accuracy").or_insert(100.into());
+        object.entry("growth").or_insert(growth.clone());
+    };
+
+    if let Some(config) = dungeon.get_mut("config").and_then(Value::as_object_mut) {
+        config.entry("items_total").or_insert(0.into());
+        config.entry("items").or_insert(Value::Array(vec![]));
+        if let Some(player) = config
+            .get_mut("player_stats")
+            .and_then(Value::as_object_mut)
+        {
+            defaults(player);
+        }
+        let entities = config.get_mut("entities").and_then(Value::as_array_mut);
+        for entity in entities
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_object_mut)
+        {
+            defaults(entity);
+            entity.entry("follow_stairs").or_insert(false.into());
+        }
+    }
+
+    let mut entities = vec![];
+    if let Some(floors) = dungeon.get_mut("floors").and_then(Value::as_array_mut) {
+        for floor in floors.iter_mut().filter_map(Value::as_object_mut) {
+            for (key, list) in floor.iter_mut() {
+                if key == "players" || key == "entities" {
+                    entities.extend(list.as_array_mut().into_iter().flatten());
+                }
+            }
+        }
+    }
+    for entity in entities.into_iter().filter_map(Value::as_object_mut) {
+        if let Some(attack) = entity.remove("attack") {
+            let attack = attack.as_i64().unwrap_or_default() as i32;
+            let stats = serde_json::to_value(CombatStats::new(attack, 0, 100));
+            entity.insert("stats".to_string(), stats.unwrap_or_default());
+        }
+    }
+}
+
+/// Migrazione dei salvataggi in cui il boss del piano era indicato tramite il suo nome.\
+/// Il nome viene rimosso dal piano e la prima entità con quel nome viene segnata come boss.
+fn migrate_boss(dungeon: &mut Value) {
+    let floors = dungeon.get_mut("floors").and_then(Value::as_array_mut);
+    for floor in floors
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+    {
+        let Some(Value::String(name)) = floor.remove("boss") else {
+            continue;
+        };
+        let entities = floor.get_mut("entities").and_then(Value::as_array_mut);
+        let boss = entities
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_object_mut)
+            .find(|entity| entity.get("name").and_then(Value::as_str) == Some(&name));
+        if let Some(boss) = boss {
+            boss.insert("boss".to_string(), true.into());
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use super::{
+    config::Config,
+    entities::Entity,
+    game::{GameOutcome, StableHasher},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    hash::Hasher,
+    io::{self, BufReader, BufWriter, ErrorKind, Write},
+};
+
+/// Numero massimo di punteggi ricordati nella classifica di ogni partita.
+pub const HIGH_SCORES_SIZE: usize = 10;
+
+/// File di base in cui viene salvata la classifica dei punteggi.
+pub const HIGH_SCORES_FILE: &str = "highscores.json";
+
+/// Punteggio ottenuto da un giocatore alla fine della sua partita.\
+/// Oltre al punteggio (vedi Statistics::get_score) vengono salvate le statistiche da cui
+/// è stato calcolato, in modo da poterle mostrare nella classifica.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Score {
+    pub name: String,
+    pub outcome: GameOutcome,
+    pub score: u32,
+    pub deepest_floor: usize,
+    pub turns: usize,
+    pub kills: usize,
+    pub items: usize,
+}
+
+impl Score {
+    /// Crea il punteggio del giocatore a partire dalle sue statistiche e dal risultato della partita.
+    pub fn new(player: &Entity, outcome: GameOutcome) -> Self {
+        let statistics = player.get_statistics();
+        Self {
+            name: player.get_name().clone(),
+            outcome,
+            score: statistics.get_score(),
+            deepest_floor: statistics.deepest_floor,
+            turns: statistics.turns,
+            kills: statistics.kills,
+            items: statistics.items,
+        }
+    }
+}
+
+/// Classifica dei punteggi migliori, salvata in un file locale.\
+/// I punteggi sono divisi per partita, identificata dal seed e dalla configurazione con cui è
+/// stata giocata (vedi HighScores::key), dato che punteggi di partite diverse non sono confrontabili.\
+/// Per ogni partita vengono ricordati solamente i migliori HIGH_SCORES_SIZE punteggi.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct HighScores {
+    tables: BTreeMap<String, Vec<Score>>,
+}
+
+impl HighScores {
+    /// Crea una classifica vuota.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Carica la classifica da un file.\
+    /// Nel caso in cui il file non esista viene restituita una classifica vuota, mentre se
+    /// non è formattato tramite json viene ritornato un errore.
+    pub fn load(filename: &str) -> io::Result<Self> {
+        let file = match File::open(filename) {
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::new()),
+            file => file?,
+        };
+        let reader = BufReader::new(file);
+        let high_scores: Self = serde_json::from_reader(reader)?;
+        Ok(high_scores)
+    }
+
+    /// Salva la classifica nel file indicato tramite serializzazione JSON.\
+    /// La classifica viene prima scritta in un file temporaneo che poi sostituisce quello
+    /// indicato, in modo da non perdere la classifica precedente se la scrittura si interrompe.\
+    /// Nel caso in cui ci siano problemi con I/O, viene ritornato un errore.
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        let temp = format!("{filename}.tmp");
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+        fs::rename(&temp, filename)
+    }
+
+    /// Calcola la chiave della partita giocata con la configurazione indicata.\
+    /// La chiave è formata dal seed e da un valore hash del resto della configurazione,
+    /// in modo che partite con lo stesso seed ma regole diverse abbiano classifiche diverse.\
+    /// Il valore hash è calcolato tramite StableHasher, quindi la chiave non cambia tra
+    /// versioni diverse di Rust.
+    pub fn key(config: &Config) -> String {
+        let rules = Config {
+            game_seed: 0,
+            ..config.clone()
+        };
+        let mut hasher = StableHasher::new();
+        hasher.write(serde_json::to_string(&rules).unwrap_or_default().as_bytes());
+        format!("{}-{:016x}", config.game_seed, hasher.finish())
+    }
+
+    /// Aggiunge il punteggio alla classifica della partita indicata.\
+    /// Viene restituita la posizione (a partire da 0) del punteggio nella classifica, oppure
+    /// None nel caso in cui non sia abbastanza alto per entrarci.\
+    /// A parità di punteggio, quello inserito prima rimane più in alto.
+    pub fn add(&mut self, key: String, score: Score) -> Option<usize> {
+        let table = self.tables.entry(key).or_default();
+        let index = table.partition_point(|other| other.score >= score.score);
+        if index >= HIGH_SCORES_SIZE {
+            return None;
+        }
+
+        table.insert(index, score);
+        table.truncate(HIGH_SCORES_SIZE);
+        Some(index)
+    }
+
+    /// Restituisce la classifica della partita indicata, dal punteggio più alto al più basso.
+    pub fn get(&self, key: &str) -> &[Score] {
+        self.tables.get(key).map_or(&[], |table| table.as_slice())
+    }
+}
@@ -0,0 +1,200 @@
+use super::{
+    cell::Cell,
+    config::Config,
+    entities::{Action, Behavior, Direction, Entity, Statistics, INVENTORY_SIZE},
+    floor::{FloorView, PathOptions},
+    game::Dungeon,
+};
+use rand::{RngCore, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Display, mem};
+
+/// Nome del giocatore utilizzato nelle partite simulate.
+const PLAYER_NAME: &str = "Simulated";
+
+/// Permette di giocare diverse partite senza interfaccia con un giocatore automatico.\
+/// Ogni partita viene giocata con un seed diverso derivato da quello della configurazione,
+/// e termina quando il giocatore esce dal gioco o quando si arriva al numero massimo di turni.\
+/// Alla fine viene generato un report con le statistiche di tutte le partite, utile per
+/// capire come una configurazione influenza la difficoltà del gioco.
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    pub config: Config,
+    pub player: Box<dyn Behavior>,
+    pub games: usize,
+    pub max_turns: usize,
+}
+
+impl Simulation {
+    /// Crea una nuova simulazione con la configurazione e il comportamento del giocatore indicati.\
+    /// Di base vengono giocate 100 partite da massimo 1000 turni ciascuna.
+    pub fn new(config: Config, player: Box<dyn Behavior>) -> Self {
+        Self {
+            config,
+            player,
+            games: 100,
+            max_turns: 1000,
+        }
+    }
+
+    /// Gioca tutte le partite della simulazione e restituisce il report finale.\
+    /// I seed delle partite sono derivati dal seed della configurazione, quindi due simulazioni
+    /// con la stessa configurazione restituiranno lo stesso report.
+    pub fn run(&self) -> SimulationReport {
+        let mut rng = Pcg32::seed_from_u64(self.config.game_seed);
+        let games = (0..self.games)
+            .map(|_| self.run_game(rng.next_u64()))
+            .collect();
+        SimulationReport::new(games)
+    }
+
+    /// Gioca una singola partita con il seed indicato e restituisce le statistiche del giocatore.
+    pub fn run_game(&self, seed: u64) -> GameReport {
+        let mut config = self.config.clone();
+        config.game_seed = seed;
+
+        let mut dungeon = Dungeon::new_with(config);
+        dungeon.add_player(PLAYER_NAME.to_string(), self.player.clone());
+        while dungeon.has_players() && dungeon.get_turn() < self.max_turns {
+            dungeon.compute_turn();
+        }
+
+        let player = dungeon
+            .get_players()
+            .chain(dungeon.get_departed())
+            .next()
+            .expect("The simulated player should be in the dungeon!");
+        GameReport {
+            seed,
+            turns: dungeon.get_turn(),
+            died: !player.is_alive(),
+            statistics: player.get_statistics().clone(),
+        }
+    }
+}
+
+/// Risultato di una singola partita simulata.\
+/// *turns* indica quanti turni sono stati calcolati dal dungeon, mentre quelli
+/// effettivamente giocati dal giocatore si trovano nelle statistiche.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GameReport {
+    pub seed: u64,
+    pub turns: usize,
+    pub died: bool,
+    pub statistics: Statistics,
+}
+
+/// Report finale di una simulazione.\
+/// Contiene i risultati di ogni partita e i valori aggregati di esse.\
+/// I danni medi sono suddivisi per tipo di effetto come nelle statistiche delle entità.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SimulationReport {
+    pub games: Vec<GameReport>,
+    pub deaths: usize,
+    pub deepest_floor: usize,
+    pub average_floor: f32,
+    pub average_turns: f32,
+    pub average_kills: f32,
+    pub average_damage: BTreeMap<String, f32>,
+}
+
+impl SimulationReport {
+    /// Crea il report a partire dai risultati delle partite, calcolando i valori aggregati.
+    pub fn new(games: Vec<GameReport>) -> Self {
+        let total = games.len().max(1) as f32;
+        let average = |value: fn(&Statistics) -> usize| {
+            games.iter().map(|g| value(&g.statistics)).sum::<usize>() as f32 / total
+        };
+
+        let mut average_damage = BTreeMap::new();
+        for (source, damage) in games.iter().flat_map(|g| &g.statistics.damage_taken) {
+            *average_damage.entry(source.clone()).or_default() += *damage as f32 / total;
+        }
+
+        Self {
+            deaths: games.iter().filter(|g| g.died).count(),
+            deepest_floor: games
+                .iter()
+                .map(|g| g.statistics.deepest_floor)
+                .max()
+                .unwrap_or(0),
+            average_floor: average(|s| s.deepest_floor),
+            average_turns: average(|s| s.turns),
+            average_kills: average(|s| s.kills),
+            average_damage,
+            games,
+        }
+    }
+}
+
+impl Display for SimulationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Games played:  {}", self.games.len())?;
+        writeln!(f, "Deaths:        {}", self.deaths)?;
+        writeln!(f, "Deepest floor: {}", self.deepest_floor)?;
+        writeln!(f, "Avg floor:     {:.2}", self.average_floor)?;
+        writeln!(f, "Avg turns:     {:.2}", self.average_turns)?;
+        writeln!(f, "Avg kills:     {:.2}", self.average_kills)?;
+        writeln!(f, "Avg damage taken:")?;
+        for (source, damage) in &self.average_damage {
+            writeln!(f, "  {source:15} {damage:.2}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Comportamento automatico pensato per le simulazioni.\
+/// L'entità attacca i nemici adiacenti, raccoglie gli oggetti su cui si trova e
+/// altrimenti si dirige verso l'uscita del piano evitando, se possibile, le celle speciali.\
+/// Nel caso in cui non trovi un percorso, si muove in una direzione casuale.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ExitSeeker {
+    action: Action,
+}
+impl ExitSeeker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+#[typetag::serde]
+impl Behavior for ExitSeeker {
+    fn update(&mut self, view: FloorView, rng: &mut Pcg32) {
+        let position = view.entity.position;
+        let enemy = view
+            .floor
+            .get_all_entities()
+            .filter(|e| e.is_alive() && e.position.distance(&position) == 1)
+            .find(|e| !view.floor.get_players().any(|p| p.position == e.position));
+
+        self.action = if let Some(enemy) = enemy {
+            Action::Attack(Direction::towards(&position, &enemy.position))
+        } else if matches!(view.floor.get_cell(&position), Cell::Item(_))
+            && view.entity.get_inventory().count() < INVENTORY_SIZE
+        {
+            Action::PickUp
+        } else {
+            let exit = view.floor.get_exit().unwrap_or(position);
+            let safe = PathOptions {
+                avoid_special: true,
+                avoid_entities: true,
+            };
+            let unsafe_path = PathOptions {
+                avoid_special: false,
+                avoid_entities: true,
+            };
+            let path = view
+                .floor
+                .find_path(&position, &exit, safe)
+                .or_else(|| view.floor.find_path(&position, &exit, unsafe_path));
+
+            match path.and_then(|path| path.first().copied()) {
+                Some(next) => Action::Move(Direction::towards(&position, &next)),
+                None => Action::Move(Direction::random(rng)),
+            }
+        };
+    }
+    fn get_next_action(&mut self, _entity: &Entity) -> Option<Action> {
+        Some(mem::take(&mut self.action))
+    }
+}
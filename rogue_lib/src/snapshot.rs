@@ -0,0 +1,184 @@
+use super::{
+    cell::Cell,
+    entities::{CombatStats, Direction, Entity, Position},
+    event::Event,
+    floor::FloorView,
+};
+use serde::{Deserialize, Serialize};
+
+/// Versione attuale della rappresentazione di una FloorSnapshot.\
+/// Viene incrementata ogni volta che la struttura cambia in modo non compatibile,
+/// in modo che chi la riceve possa accorgersene.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Fotografia di quello che una entità percepisce del piano in cui si trova.\
+/// A differenza di FloorView non ha riferimenti al piano o all'entità, e quindi può essere
+/// salvata, inviata in rete o utilizzata da bot e log senza dipendere dallo stato del gioco.\
+/// Contiene le celle viste o esplorate entro il raggio indicato, le entità visibili,
+/// le informazioni dell'entità stessa e gli eventi dell'ultimo turno.\
+/// *version* indica la versione della struttura, e deve essere uguale a SNAPSHOT_VERSION.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct FloorSnapshot {
+    pub version: u32,
+    pub turn: usize,
+    pub level: usize,
+    pub radius: usize,
+    pub entity: SelfSnapshot,
+    pub cells: Vec<CellSnapshot>,
+    pub entities: Vec<EntitySnapshot>,
+    pub events: Vec<Event>,
+}
+
+/// Tipo di una cella vista dall'entità.\
+/// Gli effetti e gli oggetti sono indicati dal loro nome.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CellKind {
+    Entrance,
+    Exit,
+    Special(String),
+    Item(String),
+    Wall,
+    Empty,
+}
+
+/// Cella vista o esplorata dall'entità.\
+/// *glyph* è il carattere con cui la cella viene rappresentata a terminale, mentre
+/// *visible* indica se la cella è visibile in questo momento o se è solo un ricordo.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CellSnapshot {
+    pub position: Position,
+    pub kind: CellKind,
+    pub glyph: char,
+    pub visible: bool,
+}
+
+/// Riassunto di una entità visibile.\
+/// *player* indica se l'entità è un giocatore.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EntitySnapshot {
+    pub name: String,
+    pub position: Position,
+    pub direction: Direction,
+    pub health: i32,
+    pub health_max: i32,
+    pub level: u32,
+    pub player: bool,
+}
+
+/// Informazioni complete dell'entità che percepisce il piano.\
+/// Gli oggetti nell'inventario, gli effetti in attesa di essere applicati e gli stati attivi
+/// sono indicati dal loro nome.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SelfSnapshot {
+    pub name: String,
+    pub position: Position,
+    pub direction: Direction,
+    pub health: i32,
+    pub health_max: i32,
+    pub stats: CombatStats,
+    pub inventory: Vec<String>,
+    pub effects: Vec<String>,
+    #[serde(default)]
+    pub statuses: Vec<String>,
+}
+
+impl FloorSnapshot {
+    /// Crea una fotografia della vista passata, considerando solamente le celle entro il raggio indicato.\
+    /// Gli eventi inclusi sono quelli avvenuti dal turno precedente del piano.
+    pub fn new(view: &FloorView, radius: usize) -> Self {
+        let entity = view.entity;
+        let turn = view.floor.get_turn();
+        let players = view
+            .floor
+            .get_players()
+            .map(|player| player.get_name())
+            .collect::<Vec<_>>();
+
+        let mut cells = vec![];
+        let mut entities = vec![];
+        for cell in view.get_grid(radius).flatten() {
+            if let Some(other) = cell.entity.filter(|other| !std::ptr::eq(*other, entity)) {
+                entities.push(EntitySnapshot {
+                    name: other.get_name().clone(),
+                    position: other.position,
+                    direction: other.direction,
+                    health: other.get_health(),
+                    health_max: other.get_health_max(),
+                    level: other.get_stats().level,
+                    player: players.contains(&other.get_name()),
+                });
+            }
+            if let Some(kind) = cell.cell {
+                cells.push(CellSnapshot {
+                    position: cell.position,
+                    kind: CellKind::from(kind),
+                    glyph: kind.as_char(),
+                    visible: cell.visible,
+                });
+            }
+        }
+
+        Self {
+            version: SNAPSHOT_VERSION,
+            turn,
+            level: view.floor.get_level(),
+            radius,
+            entity: SelfSnapshot::from(entity),
+            cells,
+            entities,
+            events: view.get_events(turn.saturating_sub(1)).cloned().collect(),
+        }
+    }
+
+    /// Indica se la fotografia ha la stessa versione di quella attuale.
+    pub fn is_compatible(&self) -> bool {
+        self.version == SNAPSHOT_VERSION
+    }
+
+    /// Restituisce la cella nella posizione indicata, se è stata vista dall'entità.
+    pub fn get_cell(&self, position: &Position) -> Option<&CellSnapshot> {
+        self.cells.iter().find(|cell| cell.position == *position)
+    }
+
+    /// Restituisce l'entità visibile nella posizione indicata, se esiste.
+    pub fn get_entity_at(&self, position: &Position) -> Option<&EntitySnapshot> {
+        self.entities
+            .iter()
+            .find(|entity| entity.position == *position)
+    }
+}
+
+impl From<&Cell> for CellKind {
+    fn from(cell: &Cell) -> Self {
+        match cell {
+            Cell::Entrance => CellKind::Entrance,
+            Cell::Exit => CellKind::Exit,
+            Cell::Special(effect) => CellKind::Special(effect.get_name()),
+            Cell::Item(item) => CellKind::Item(item.get_name()),
+            Cell::Wall => CellKind::Wall,
+            Cell::Empty => CellKind::Empty,
+        }
+    }
+}
+
+impl From<&Entity> for SelfSnapshot {
+    fn from(entity: &Entity) -> Self {
+        Self {
+            name: entity.get_name().clone(),
+            position: entity.position,
+            direction: entity.direction,
+            health: entity.get_health(),
+            health_max: entity.get_health_max(),
+            stats: entity.get_stats().clone(),
+            inventory: entity.get_inventory().map(|item| item.get_name()).collect(),
+            effects: entity
+                .get_effects()
+                .map(|effect| effect.get_name())
+                .collect(),
+            statuses: entity
+                .get_statuses()
+                .map(|active| active.get_status().get_name())
+                .collect(),
+        }
+    }
+}
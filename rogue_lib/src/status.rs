@@ -0,0 +1,427 @@
+use super::{
+    cell::{Cell, Effect, EffectColor},
+    entities::{Action, Entity, Position},
+    floor::Floor,
+};
+use dyn_clone::{clone_trait_object, DynClone};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Trait che permette di implementare uno stato che rimane applicato ad una entità
+/// per un certo numero di turni.\
+/// Come per Effect, il trait è taggato con typetag in modo che possa essere utilizzato
+/// nella serializzazione e deserializzazione di serde; nell'implementazione di una
+/// struttura concreta va quindi messo sopra impl X for Status:\
+/// #\[typetag::serde\]\
+/// \
+/// Uno stato può reagire a tre momenti della sua vita tramite gli hook *on_apply*,
+/// *on_tick* e *on_expire*, e può modificare l'entità finchè rimane attivo tramite i Modifiers.\
+/// Due stati sono considerati dello stesso tipo se hanno lo stesso nome in typetag,
+/// ed in quel caso viene usata la politica indicata da get_stacking.
+#[typetag::serde(tag = "type")]
+pub trait Status: DynClone + core::fmt::Debug {
+    /// Restituisce il nome dello stato in modo da poterlo mostrare al giocatore.
+    fn get_name(&self) -> String;
+    /// Restituisce una breve descrizione di cosa fa lo stato a chi lo subisce.
+    fn get_description(&self) -> String;
+    /// Restituisce per quanti turni lo stato rimane attivo una volta applicato.
+    fn get_duration(&self) -> u32;
+    /// Indica cosa succede se lo stato viene applicato ad una entità che ne ha già uno dello stesso tipo.\
+    /// Di base la durata viene rinnovata.
+    fn get_stacking(&self) -> Stacking {
+        Stacking::Refresh
+    }
+    /// Restituisce le modifiche che lo stato fa all'entità finchè rimane attivo.\
+    /// Di base uno stato non modifica nulla.
+    fn get_modifiers(&self) -> Modifiers {
+        Modifiers::default()
+    }
+    /// Chiamato quando lo stato viene aggiunto all'entità come nuova istanza.
+    fn on_apply(&self, _entity: &mut Entity, _floor: &mut Floor) {}
+    /// Chiamato una volta per turno finchè lo stato è attivo.
+    fn on_tick(&self, _entity: &mut Entity, _floor: &mut Floor) {}
+    /// Chiamato dopo l'ultimo turno in cui lo stato è attivo, prima che venga rimosso.
+    fn on_expire(&self, _entity: &mut Entity, _floor: &mut Floor) {}
+    /// Ritorna un carattere che rappresenta lo stato.
+    fn as_char(&self) -> char {
+        '?'
+    }
+    /// Ritorna il colore con cui mostrare lo stato.
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Magenta
+    }
+}
+clone_trait_object!(Status);
+
+/// Politica da usare quando uno stato viene applicato ad una entità che ne ha già uno dello stesso tipo.\
+/// *Refresh* sostituisce lo stato attivo con il nuovo, tenendo la durata rimanente più lunga tra i due.\
+/// *Extend* lascia lo stato attivo e somma alla sua durata rimanente quella del nuovo.\
+/// *Stack* aggiunge il nuovo stato come istanza indipendente, in modo che i loro effetti si sommino.\
+/// *Ignore* scarta il nuovo stato, lasciando quello attivo invariato.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Stacking {
+    Refresh,
+    Extend,
+    Stack,
+    Ignore,
+}
+
+/// Modifiche che gli stati attivi fanno ad una entità.\
+/// *attack* viene sommato all'attacco dell'entità.\
+/// *sight* viene sommato al raggio di visione dell'entità, che però rimane sempre di almeno 1.\
+/// *speed* indica quante azioni in più fa l'entità per turno; se negativo l'entità
+/// agisce solamente una volta ogni 1 - speed turni.\
+/// *stunned* indica che l'entità non può fare nessuna azione.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub attack: i32,
+    pub sight: i32,
+    pub speed: i32,
+    pub stunned: bool,
+}
+
+impl Modifiers {
+    /// Combina le modifiche di due stati sommandone i valori.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            attack: self.attack + other.attack,
+            sight: self.sight + other.sight,
+            speed: self.speed + other.speed,
+            stunned: self.stunned || other.stunned,
+        }
+    }
+}
+
+/// Stato attivo su una entità insieme al numero di turni che gli rimangono.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ActiveStatus {
+    pub(crate) status: Box<dyn Status>,
+    pub(crate) remaining: u32,
+}
+
+impl ActiveStatus {
+    /// Restituisce lo stato attivo.
+    pub fn get_status(&self) -> &dyn Status {
+        self.status.as_ref()
+    }
+
+    /// Restituisce per quanti turni lo stato rimarrà ancora attivo.
+    pub fn get_remaining(&self) -> u32 {
+        self.remaining
+    }
+}
+
+/// Implementa Effect per uno stato, in modo che possa essere messo in una cella
+/// speciale o in una pozione.\
+/// L'effetto non è persistente e, una volta applicato, aggiunge lo stato all'entità.
+macro_rules! status_effect {
+    ($status:ty) => {
+        #[typetag::serde]
+        impl Effect for $status {
+            fn is_persistent(&self) -> bool {
+                false
+            }
+            fn apply_to(&self, entity: &mut Entity, floor: &mut Floor) {
+                entity.add_status(Box::new(self.clone()), floor);
+            }
+            fn get_name(&self) -> String {
+                Status::get_name(self)
+            }
+            fn get_description(&self) -> String {
+                Status::get_description(self)
+            }
+            fn as_char(&self) -> char {
+                Status::as_char(self)
+            }
+            fn get_color(&self) -> EffectColor {
+                Status::get_color(self)
+            }
+        }
+    };
+}
+
+/// Veleno che infligge un danno ad ogni turno.\
+/// Più dosi di veleno si sommano, ognuna con la propria durata.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Poison {
+    pub damage: i32,
+    pub duration: u32,
+}
+#[typetag::serde]
+impl Status for Poison {
+    fn get_name(&self) -> String {
+        "Poison".to_string()
+    }
+    fn get_description(&self) -> String {
+        format!(
+            "Deals {} damage per turn for {} turns, doses stack",
+            self.damage, self.duration
+        )
+    }
+    fn get_duration(&self) -> u32 {
+        self.duration
+    }
+    fn get_stacking(&self) -> Stacking {
+        Stacking::Stack
+    }
+    fn on_tick(&self, entity: &mut Entity, _floor: &mut Floor) {
+        entity.apply_damage(self.damage);
+    }
+    fn as_char(&self) -> char {
+        '×'
+    }
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Green
+    }
+}
+status_effect!(Poison);
+
+/// Rigenerazione che cura l'entità ad ogni turno.\
+/// Una nuova rigenerazione rinnova quella attiva.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Regeneration {
+    pub heal: i32,
+    pub duration: u32,
+}
+#[typetag::serde]
+impl Status for Regeneration {
+    fn get_name(&self) -> String {
+        "Regeneration".to_string()
+    }
+    fn get_description(&self) -> String {
+        format!(
+            "Heals {} health per turn for {} turns",
+            self.heal, self.duration
+        )
+    }
+    fn get_duration(&self) -> u32 {
+        self.duration
+    }
+    fn on_tick(&self, entity: &mut Entity, _floor: &mut Floor) {
+        entity.apply_damage(-self.heal);
+    }
+    fn as_char(&self) -> char {
+        '♥'
+    }
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Cyan
+    }
+}
+status_effect!(Regeneration);
+
+/// Velocità che permette all'entità di fare un'azione in più ogni turno.\
+/// Una nuova velocità allunga la durata di quella attiva.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Haste {
+    pub duration: u32,
+}
+#[typetag::serde]
+impl Status for Haste {
+    fn get_name(&self) -> String {
+        "Haste".to_string()
+    }
+    fn get_description(&self) -> String {
+        format!(
+            "Grants an extra action per turn for {} turns",
+            self.duration
+        )
+    }
+    fn get_duration(&self) -> u32 {
+        self.duration
+    }
+    fn get_stacking(&self) -> Stacking {
+        Stacking::Extend
+    }
+    fn get_modifiers(&self) -> Modifiers {
+        Modifiers {
+            speed: 1,
+            ..Default::default()
+        }
+    }
+    fn as_char(&self) -> char {
+        '»'
+    }
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Blue
+    }
+}
+status_effect!(Haste);
+
+/// Lentezza che fa agire l'entità solamente un turno su due.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Slow {
+    pub duration: u32,
+}
+#[typetag::serde]
+impl Status for Slow {
+    fn get_name(&self) -> String {
+        "Slow".to_string()
+    }
+    fn get_description(&self) -> String {
+        format!("Acts only every other turn for {} turns", self.duration)
+    }
+    fn get_duration(&self) -> u32 {
+        self.duration
+    }
+    fn get_modifiers(&self) -> Modifiers {
+        Modifiers {
+            speed: -1,
+            ..Default::default()
+        }
+    }
+    fn as_char(&self) -> char {
+        '«'
+    }
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Blue
+    }
+}
+status_effect!(Slow);
+
+/// Cecità che riduce il raggio di visione dell'entità della quantità indicata.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Blindness {
+    pub radius: i32,
+    pub duration: u32,
+}
+#[typetag::serde]
+impl Status for Blindness {
+    fn get_name(&self) -> String {
+        "Blindness".to_string()
+    }
+    fn get_description(&self) -> String {
+        format!(
+            "Reduces sight by {} cells for {} turns",
+            self.radius, self.duration
+        )
+    }
+    fn get_duration(&self) -> u32 {
+        self.duration
+    }
+    fn get_modifiers(&self) -> Modifiers {
+        Modifiers {
+            sight: -self.radius,
+            ..Default::default()
+        }
+    }
+    fn as_char(&self) -> char {
+        '°'
+    }
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Yellow
+    }
+}
+status_effect!(Blindness);
+
+/// Stordimento che impedisce all'entità di agire.\
+/// Quando viene applicato annulla l'eventuale azione in attesa e, finchè è attivo,
+/// l'entità non può essere stordita di nuovo.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Stun {
+    pub duration: u32,
+}
+#[typetag::serde]
+impl Status for Stun {
+    fn get_name(&self) -> String {
+        "Stun".to_string()
+    }
+    fn get_description(&self) -> String {
+        format!("Prevents any action for {} turns", self.duration)
+    }
+    fn get_duration(&self) -> u32 {
+        self.duration
+    }
+    fn get_stacking(&self) -> Stacking {
+        Stacking::Ignore
+    }
+    fn get_modifiers(&self) -> Modifiers {
+        Modifiers {
+            stunned: true,
+            ..Default::default()
+        }
+    }
+    fn on_apply(&self, entity: &mut Entity, _floor: &mut Floor) {
+        entity.buffer = Action::DoNothing;
+    }
+    fn as_char(&self) -> char {
+        '*'
+    }
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Yellow
+    }
+}
+status_effect!(Stun);
+
+/// Forza che aumenta temporaneamente l'attacco dell'entità.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Strength {
+    pub bonus: i32,
+    pub duration: u32,
+}
+#[typetag::serde]
+impl Status for Strength {
+    fn get_name(&self) -> String {
+        "Strength".to_string()
+    }
+    fn get_description(&self) -> String {
+        format!(
+            "Increases attack by {} for {} turns",
+            self.bonus, self.duration
+        )
+    }
+    fn get_duration(&self) -> u32 {
+        self.duration
+    }
+    fn get_modifiers(&self) -> Modifiers {
+        Modifiers {
+            attack: self.bonus,
+            ..Default::default()
+        }
+    }
+    fn as_char(&self) -> char {
+        '↑'
+    }
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Red
+    }
+}
+status_effect!(Strength);
+
+/// Trappola che teletrasporta chi ci passa sopra in una cella libera casuale del piano.\
+/// La trappola rimane nel piano dopo essere stata attivata; nel caso in cui non venga trovata
+/// nessuna cella libera l'entità rimane dove si trova.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Teleport;
+#[typetag::serde]
+impl Effect for Teleport {
+    fn is_persistent(&self) -> bool {
+        true
+    }
+    fn apply_to(&self, entity: &mut Entity, floor: &mut Floor) {
+        let size = floor.get_size();
+        let cells = (0..size)
+            .flat_map(|x| (0..size).map(move |y| Position(x, y)))
+            .filter(|pos| *pos != entity.position && !matches!(floor.get_cell(pos), Cell::Wall))
+            .collect::<Vec<_>>();
+        if cells.is_empty() {
+            return;
+        }
+
+        let random = cells[floor.get_rng().gen_range(0..cells.len())];
+        if let Some(position) = floor.find_free_cell_near(&random) {
+            entity.position = position;
+        }
+    }
+    fn get_name(&self) -> String {
+        "Teleport trap".to_string()
+    }
+    fn get_description(&self) -> String {
+        "Teleports to a random free cell of the floor".to_string()
+    }
+    fn as_char(&self) -> char {
+        '○'
+    }
+    fn get_color(&self) -> EffectColor {
+        EffectColor::Blue
+    }
+}
@@ -0,0 +1,598 @@
+use super::{
+    cell::{Cell, Effect, EffectColor},
+    entities::{Direction, Entity, Position},
+    floor::{CellView, FloorView},
+    COLOR_ENEMY, COLOR_EXPLORED, COLOR_ITEM, COLOR_PLAYER, COLOR_PLAYER_HEALTH, COLOR_RESET,
+    MESSAGES_SHOWN,
+};
+use std::{collections::BTreeMap, io::Write};
+
+/// Larghezza del pannello laterale con le informazioni del giocatore.
+pub const PANEL_WIDTH: usize = 32;
+
+/// Numero di caratteri usati per disegnare una cella del piano.
+const CELL_WIDTH: usize = 2;
+
+/// Sequenza che pulisce tutto lo schermo del terminale.
+const CLEAR_SCREEN: &str = "\x1b[2J";
+
+/// Colore del cursore della modalità look, che inverte i colori della cella.
+const COLOR_CURSOR: &str = "\x1b[7m";
+
+/// Carattere dello schermo insieme al colore con cui deve essere disegnato.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Glyph {
+    pub ch: char,
+    pub color: &'static str,
+}
+
+impl Glyph {
+    /// Crea un carattere con il colore indicato.
+    pub fn new(ch: char, color: &'static str) -> Self {
+        Self { ch, color }
+    }
+}
+
+/// Contenuto dello schermo del terminale, formato da una matrice di caratteri colorati.\
+/// Il frame viene prima composto in memoria e poi disegnato tramite Screen, che confronta
+/// il nuovo frame con quello precedente in modo da ridisegnare solo le celle cambiate.\
+/// Le scritture fuori dal frame vengono ignorate, quindi si può disegnare senza preoccuparsi
+/// della dimensione del terminale.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<Glyph>>,
+}
+
+impl Frame {
+    /// Crea un frame vuoto delle dimensioni indicate.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![vec![Glyph::new(' ', ""); width]; height],
+        }
+    }
+
+    /// Restituisce la larghezza del frame in caratteri.
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Restituisce l'altezza del frame in righe.
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Restituisce il carattere alla colonna e riga indicate, se si trova dentro il frame.
+    pub fn get(&self, x: usize, y: usize) -> Option<&Glyph> {
+        self.cells.get(y).and_then(|row| row.get(x))
+    }
+
+    /// Restituisce il testo della riga indicata, senza colori.
+    pub fn get_line(&self, y: usize) -> String {
+        self.cells
+            .get(y)
+            .map(|row| row.iter().map(|glyph| glyph.ch).collect())
+            .unwrap_or_default()
+    }
+
+    /// Scrive il carattere alla colonna e riga indicate.
+    pub fn put(&mut self, x: usize, y: usize, ch: char, color: &'static str) {
+        if let Some(glyph) = self.cells.get_mut(y).and_then(|row| row.get_mut(x)) {
+            *glyph = Glyph::new(ch, color);
+        }
+    }
+
+    /// Scrive il testo a partire dalla colonna e riga indicate, troncandolo a *max* caratteri.
+    pub fn put_str(&mut self, x: usize, y: usize, max: usize, text: &str, color: &'static str) {
+        for (i, ch) in text.chars().take(max).enumerate() {
+            self.put(x + i, y, ch, color);
+        }
+    }
+
+    /// Disegna una cornice con il titolo indicato, pulendo tutto il suo interno.\
+    /// La cornice è composta dagli stessi caratteri usati da box_of.
+    pub fn put_box(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        title: &str,
+        color: &'static str,
+    ) {
+        if width < 2 || height < 2 {
+            return;
+        }
+
+        for row in y..y + height {
+            for col in x..x + width {
+                let ch = match (
+                    row == y,
+                    row == y + height - 1,
+                    col == x,
+                    col == x + width - 1,
+                ) {
+                    (true, _, true, _) => '╔',
+                    (true, _, _, true) => '╗',
+                    (_, true, true, _) => '╚',
+                    (_, true, _, true) => '╝',
+                    (true, _, _, _) | (_, true, _, _) => '═',
+                    (_, _, true, _) | (_, _, _, true) => '║',
+                    _ => ' ',
+                };
+                self.put(col, row, ch, color);
+            }
+        }
+
+        let title_len = title.chars().count().min(width - 2);
+        let start = x + (width - title_len) / 2;
+        self.put_str(start, y, title_len, title, color);
+    }
+
+    /// Restituisce le sequenze da scrivere nel terminale per passare dal frame precedente a questo.\
+    /// Vengono ridisegnati solamente i caratteri cambiati; nel caso in cui non ci sia un frame
+    /// precedente o abbia una dimensione diversa (ad esempio perchè il terminale è stato
+    /// ridimensionato), lo schermo viene pulito e ridisegnato completamente.
+    pub fn diff(&self, previous: Option<&Frame>) -> String {
+        let previous = previous.filter(|p| p.width == self.width && p.height == self.height);
+        let mut output = String::new();
+        if previous.is_none() {
+            output.push_str(CLEAR_SCREEN);
+        }
+
+        for (y, row) in self.cells.iter().enumerate() {
+            let changed = |x: usize| previous.is_none_or(|p| p.cells[y][x] != row[x]);
+            let mut x = 0;
+            while x < self.width {
+                if !changed(x) {
+                    x += 1;
+                    continue;
+                }
+
+                output.push_str(&format!("\x1b[{};{}H", y + 1, x + 1));
+                let mut color = "";
+                while x < self.width && changed(x) {
+                    let glyph = &row[x];
+                    if glyph.color != color {
+                        output.push_str(COLOR_RESET);
+                        output.push_str(glyph.color);
+                        color = glyph.color;
+                    }
+                    output.push(glyph.ch);
+                    x += 1;
+                }
+                output.push_str(COLOR_RESET);
+            }
+        }
+        output
+    }
+}
+
+/// Schermo del terminale che ricorda l'ultimo frame disegnato.\
+/// In questo modo ogni nuovo frame ridisegna solamente le celle cambiate, evitando che
+/// lo schermo sfarfalli ad ogni turno.\
+/// Sotto al frame viene lasciata una riga per i messaggi che chiedono un input all'utente.
+#[derive(Clone, Debug, Default)]
+pub struct Screen {
+    last: Option<Frame>,
+}
+
+impl Screen {
+    /// Restituisce la dimensione attuale del terminale come (larghezza, altezza).
+    pub fn size() -> (usize, usize) {
+        let (rows, cols) = console::Term::stdout().size();
+        (cols as usize, rows as usize)
+    }
+
+    /// Restituisce l'ultimo frame disegnato, se esiste.
+    pub fn get_last(&self) -> Option<&Frame> {
+        self.last.as_ref()
+    }
+
+    /// Disegna il frame nel terminale, ridisegnando solo quello che è cambiato dall'ultimo.
+    pub fn render(&mut self, frame: Frame) {
+        let mut term = console::Term::stdout();
+        let _ = term.write_all(frame.diff(self.last.as_ref()).as_bytes());
+        let _ = term.flush();
+        self.last = Some(frame);
+    }
+
+    /// Scrive il messaggio nella riga sotto al frame, lasciando il cursore alla fine di esso.
+    pub fn prompt(&self, text: &str) {
+        let row = self.last.as_ref().map_or(0, Frame::get_height) + 1;
+        let mut term = console::Term::stdout();
+        let _ = write!(term, "\x1b[{row};1H\x1b[2K{text}");
+        let _ = term.flush();
+    }
+}
+
+/// Compone il frame con la vista del piano, delle dimensioni indicate.\
+/// A sinistra si trova la porzione del piano attorno all'entità, grande quanto lo spazio
+/// disponibile, con sotto gli ultimi messaggi; a destra si trova il pannello laterale con le
+/// statistiche, l'inventario, gli effetti in attesa, la legenda e la minimappa del piano.
+pub fn draw_view(view: &FloorView, width: usize, height: usize) -> Frame {
+    let mut frame = Frame::new(width, height);
+    let (map_width, map_height) = map_size(width, height);
+
+    draw_map(&mut frame, view, map_width, map_height);
+    draw_messages(&mut frame, view, map_height, map_width);
+    draw_panel(&mut frame, view, map_width, width - map_width);
+    frame
+}
+
+/// Disegna il cursore della modalità look nella posizione indicata del piano.\
+/// Al posto dei messaggi viene mostrata la descrizione di quello che si trova sotto al cursore.
+pub fn draw_look(frame: &mut Frame, look: &LookMap, cursor: &Position) {
+    if let Some((x, y)) = look.get_frame_position(cursor) {
+        for col in x..x + CELL_WIDTH {
+            if let Some(glyph) = frame.get(col, y).cloned() {
+                frame.put(col, y, glyph.ch, COLOR_CURSOR);
+            }
+        }
+    }
+
+    let (map_width, map_height) = map_size(frame.get_width(), frame.get_height());
+    for y in map_height..frame.get_height() {
+        frame.put_str(0, y, map_width, &" ".repeat(map_width), "");
+    }
+    for (i, line) in look
+        .describe(cursor)
+        .iter()
+        .take(MESSAGES_SHOWN)
+        .enumerate()
+    {
+        frame.put_str(1, map_height + i, map_width.saturating_sub(1), line, "");
+    }
+}
+
+/// Restituisce la dimensione della zona del frame occupata dalla mappa come (larghezza, altezza).\
+/// Sotto di essa si trovano i messaggi, mentre a destra il pannello laterale.
+fn map_size(width: usize, height: usize) -> (usize, usize) {
+    let panel = PANEL_WIDTH.min(width);
+    (width - panel, height.saturating_sub(MESSAGES_SHOWN))
+}
+
+/// Restituisce le celle del piano mostrate nella mappa insieme alla loro posizione nel frame.\
+/// La mappa è disegnata dentro una cornice e ogni cella occupa CELL_WIDTH caratteri.
+fn map_cells<'a>(
+    view: &'a FloorView,
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (usize, usize, CellView<'a>)> {
+    let (map_width, map_height) = map_size(width, height);
+    let cols = map_width.saturating_sub(2) / CELL_WIDTH;
+    let rows = map_height.saturating_sub(2);
+    view.get_viewport(cols, rows)
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.enumerate()
+                .map(move |(x, cell)| (1 + x * CELL_WIDTH, 1 + y, cell))
+        })
+}
+
+/// Celle mostrate nella mappa di un frame, insieme alla loro posizione in esso e alla loro descrizione.\
+/// Viene usata dalla modalità look dell'interfaccia per muovere un cursore sulla mappa e
+/// descrivere quello che si trova nelle celle, anche quando la vista del piano non esiste più.
+#[derive(Clone, Debug, Default)]
+pub struct LookMap {
+    cells: BTreeMap<Position, (usize, usize, Vec<String>)>,
+}
+
+impl LookMap {
+    /// Crea la mappa delle celle mostrate dal frame creato con draw_view e le stesse dimensioni.
+    pub fn new(view: &FloorView, width: usize, height: usize) -> Self {
+        let cells = map_cells(view, width, height)
+            .map(|(x, y, cell)| (cell.position, (x, y, describe(view, &cell))))
+            .collect();
+        Self { cells }
+    }
+
+    /// Restituisce la colonna e la riga del frame in cui è disegnata la posizione indicata.
+    pub fn get_frame_position(&self, position: &Position) -> Option<(usize, usize)> {
+        self.cells.get(position).map(|(x, y, _)| (*x, *y))
+    }
+
+    /// Restituisce la descrizione di quello che si trova nella posizione indicata.\
+    /// Nel caso in cui la posizione non sia mostrata nel frame, la descrizione è vuota.
+    pub fn describe(&self, position: &Position) -> &[String] {
+        self.cells
+            .get(position)
+            .map_or(&[], |(_, _, description)| description.as_slice())
+    }
+
+    /// Restituisce la posizione raggiunta muovendosi di una cella nella direzione indicata.\
+    /// Nel caso in cui la nuova posizione non sia mostrata nel frame, si rimane fermi.
+    pub fn step(&self, position: Position, direction: Direction) -> Position {
+        let mut next = position;
+        direction.move_from(&mut next);
+        match self.cells.contains_key(&next) {
+            true => next,
+            false => position,
+        }
+    }
+}
+
+/// Restituisce la descrizione di quello che l'entità della vista sa della cella indicata.
+fn describe(view: &FloorView, cell: &CellView) -> Vec<String> {
+    let Some(content) = cell.cell else {
+        return vec!["Unexplored".to_string()];
+    };
+
+    let mut lines = vec![];
+    if let Some(entity) = cell.entity {
+        let you = if std::ptr::eq(entity, view.entity) {
+            " (you)"
+        } else {
+            ""
+        };
+        lines.push(format!(
+            "{}{you}: Lv.{} HP {}/{} ATK {}",
+            entity.get_name(),
+            entity.get_stats().level,
+            entity.get_health(),
+            entity.get_health_max(),
+            entity.get_attack()
+        ));
+    }
+    lines.push(match content {
+        Cell::Entrance => "Entrance of the floor".to_string(),
+        Cell::Exit if view.floor.is_exit_locked() => {
+            "Exit, locked until the boss is defeated".to_string()
+        }
+        Cell::Exit => "Exit to the next floor".to_string(),
+        Cell::Special(effect) => format!("{}: {}", effect.get_name(), effect.get_description()),
+        Cell::Item(item) => format!("Item: {}", item.get_name()),
+        Cell::Wall => "Wall".to_string(),
+        Cell::Empty => "Empty floor".to_string(),
+    });
+    if !cell.visible {
+        lines.push("Not visible, this is how you remember it".to_string());
+    }
+    lines
+}
+
+/// Restituisce il colore del terminale con cui mostrare l'effetto.
+fn effect_color(effect: &dyn Effect) -> &'static str {
+    ansi_color(effect.get_color())
+}
+
+/// Trasforma il colore di un effetto o di uno stato nel codice ANSI corrispondente.
+fn ansi_color(color: EffectColor) -> &'static str {
+    match color {
+        EffectColor::Red => "\x1b[91m",
+        EffectColor::Green => "\x1b[92m",
+        EffectColor::Yellow => "\x1b[33m",
+        EffectColor::Blue => "\x1b[94m",
+        EffectColor::Magenta => "\x1b[95m",
+        EffectColor::Cyan => "\x1b[96m",
+    }
+}
+
+/// Disegna una finestra con il titolo e le righe indicate al centro del frame.
+pub fn draw_popup(frame: &mut Frame, title: &str, lines: &[String], color: &'static str) {
+    let longest = lines.iter().map(|line| line.chars().count()).max();
+    let width = longest.unwrap_or(0).max(title.chars().count()) + 4;
+    let height = lines.len() + 2;
+    let x = frame.get_width().saturating_sub(width) / 2;
+    let y = frame.get_height().saturating_sub(height) / 2;
+
+    frame.put_box(x, y, width, height, title, color);
+    for (i, line) in lines.iter().enumerate() {
+        frame.put_str(x + 2, y + 1 + i, width - 4, line, color);
+    }
+}
+
+/// Disegna la porzione di piano visibile, all'interno di una cornice con il livello del piano.
+fn draw_map(frame: &mut Frame, view: &FloorView, width: usize, height: usize) {
+    let title = format!(" Floor lv.{:2} ", view.floor.get_level());
+    frame.put_box(0, 0, width, height, &title, "");
+
+    let (frame_width, frame_height) = (frame.get_width(), frame.get_height());
+    for (x, y, cell) in map_cells(view, frame_width, frame_height) {
+        let [first, second] = cell_glyphs(view, &cell);
+        frame.put(x, y, first.ch, first.color);
+        frame.put(x + 1, y, second.ch, second.color);
+    }
+}
+
+/// Restituisce i caratteri con cui disegnare la cella indicata.\
+/// Le celle già esplorate ma non visibili in questo momento vengono mostrate in modo sbiadito.
+fn cell_glyphs(view: &FloorView, cell: &CellView) -> [Glyph; 2] {
+    let empty = Glyph::new(' ', "");
+    if let Some(entity) = cell.entity {
+        let color = match view.entity.position == entity.position {
+            true => COLOR_PLAYER,
+            false => COLOR_ENEMY,
+        };
+        return [Glyph::new(entity.direction.as_char(), color), empty];
+    }
+
+    let Some(content) = cell.cell else {
+        return [empty.clone(), empty];
+    };
+    let ch = content.as_char();
+    let color = if cell.visible { "" } else { COLOR_EXPLORED };
+    match content {
+        Cell::Special(effect) if cell.visible => {
+            [Glyph::new(ch, effect_color(effect.as_ref())), empty]
+        }
+        Cell::Item(_) if cell.visible => [Glyph::new(ch, COLOR_ITEM), empty],
+        Cell::Wall => [Glyph::new(ch, color), Glyph::new(ch, color)],
+        _ => [Glyph::new(ch, color), empty],
+    }
+}
+
+/// Disegna gli ultimi eventi avvenuti nel piano; quelli che riguardano l'entità sono evidenziati.
+fn draw_messages(frame: &mut Frame, view: &FloorView, y: usize, width: usize) {
+    let name = view.entity.get_name();
+    let events = view.get_events(0).collect::<Vec<_>>();
+    let skip = events.len().saturating_sub(MESSAGES_SHOWN);
+    for (i, event) in events[skip..].iter().enumerate() {
+        let color = if event.involves(name) {
+            COLOR_PLAYER
+        } else {
+            ""
+        };
+        frame.put_str(1, y + i, width.saturating_sub(1), &event.to_string(), color);
+    }
+}
+
+/// Disegna il pannello laterale con le informazioni sull'entità della vista.\
+/// Le sezioni che non ci stanno nell'altezza del frame vengono tagliate, mentre la minimappa
+/// occupa tutto lo spazio rimasto in fondo al pannello.
+fn draw_panel(frame: &mut Frame, view: &FloorView, x: usize, width: usize) {
+    let entity = view.entity;
+    let height = frame.get_height();
+    let title = format!(" {} ", entity.get_name());
+    frame.put_box(x, 0, width, height, &title, "");
+
+    let inner = width.saturating_sub(4);
+    let mut lines = vec![];
+    lines.push(health_bar(entity, inner));
+    let stats = entity.get_stats();
+    let needed = entity.get_growth().experience_for(stats.level);
+    lines.push((
+        format!("Lv.{} ({}/{needed} XP)", stats.level, stats.experience),
+        "",
+    ));
+    lines.push((
+        format!(
+            "ATK {} DEF {} ACC {}%",
+            entity.get_attack(),
+            stats.defense,
+            stats.accuracy
+        ),
+        "",
+    ));
+
+    lines.push((String::new(), ""));
+    lines.push(("Inventory:".to_string(), ""));
+    let items = entity
+        .get_inventory()
+        .enumerate()
+        .map(|(i, item)| (format!(" [{}] {}", i + 1, item.get_name()), COLOR_ITEM));
+    section(&mut lines, items);
+
+    lines.push(("Effects:".to_string(), ""));
+    let effects = entity.get_effects().map(|effect| {
+        (
+            format!(" {}", effect.get_name()),
+            effect_color(effect.as_ref()),
+        )
+    });
+    let statuses = entity.get_statuses().map(|active| {
+        let status = active.get_status();
+        (
+            format!(" {} ({})", status.get_name(), active.get_remaining()),
+            ansi_color(status.get_color()),
+        )
+    });
+    let effects = effects.chain(statuses);
+    section(&mut lines, effects);
+
+    lines.push(("Legend:".to_string(), ""));
+    section(&mut lines, legend(view).into_iter());
+
+    let bottom = height.saturating_sub(1);
+    let mut y = 1;
+    for (line, color) in lines.into_iter().take(bottom.saturating_sub(1)) {
+        frame.put_str(x + 2, y, inner, &line, color);
+        y += 1;
+    }
+
+    if y + 2 < bottom {
+        frame.put_str(x + 2, y, inner, "Map:", "");
+        draw_minimap(frame, view, x + 2, y + 1, inner, bottom - y - 1);
+    }
+}
+
+/// Aggiunge alle righe del pannello quelle della sezione, oppure "-" se è vuota.
+fn section(
+    lines: &mut Vec<(String, &'static str)>,
+    content: impl Iterator<Item = (String, &'static str)>,
+) {
+    let start = lines.len();
+    lines.extend(content);
+    if lines.len() == start {
+        lines.push((" -".to_string(), COLOR_EXPLORED));
+    }
+}
+
+/// Restituisce la barra della vita dell'entità, lunga quanto la larghezza indicata.
+fn health_bar(entity: &Entity, width: usize) -> (String, &'static str) {
+    let numbers = format!(" {}/{}", entity.get_health(), entity.get_health_max());
+    let size = width.saturating_sub(numbers.len() + 2);
+    let max = entity.get_health_max().max(1) as usize;
+    let filled = (entity.get_health().max(0) as usize * size / max).min(size);
+    let bar = format!(
+        "[{}{}]{numbers}",
+        "■".repeat(filled),
+        " ".repeat(size - filled)
+    );
+    (bar, COLOR_PLAYER_HEALTH)
+}
+
+/// Restituisce la legenda degli effetti visibili in questo momento nel piano.\
+/// Ogni simbolo diverso viene mostrato una sola volta, insieme all'effetto che rappresenta.
+fn legend(view: &FloorView) -> Vec<(String, &'static str)> {
+    let mut symbols: BTreeMap<char, (String, &'static str)> = BTreeMap::new();
+    for row in view.get_grid(view.floor.get_size()) {
+        for cell in row.filter(|cell| cell.visible) {
+            if let Some(Cell::Special(effect)) = cell.cell {
+                let color = effect_color(effect.as_ref());
+                symbols
+                    .entry(effect.as_char())
+                    .or_insert_with(|| (effect.get_name(), color));
+            }
+        }
+    }
+    symbols
+        .into_iter()
+        .map(|(ch, (name, color))| (format!(" {ch} {name}"), color))
+        .collect()
+}
+
+/// Disegna la minimappa delle zone esplorate del piano nello spazio indicato.\
+/// Ogni carattere della minimappa rappresenta un blocco di celle del piano, in modo che
+/// tutto il piano stia nello spazio disponibile.
+fn draw_minimap(frame: &mut Frame, view: &FloorView, x: usize, y: usize, w: usize, h: usize) {
+    let size = view.floor.get_size();
+    if w == 0 || h == 0 || size == 0 {
+        return;
+    }
+
+    let level = view.floor.get_level();
+    let block_x = size.div_ceil(w);
+    let block_y = size.div_ceil(h);
+    let offset = w.saturating_sub(size.div_ceil(block_x)) / 2;
+    for row in 0..size.div_ceil(block_y) {
+        for col in 0..size.div_ceil(block_x) {
+            let top = size - row * block_y;
+            let xs = col * block_x..((col + 1) * block_x).min(size);
+            let ys = top.saturating_sub(block_y)..top;
+            let positions = xs.flat_map(|px| ys.clone().map(move |py| Position(px, py)));
+
+            let mut glyph = Glyph::new(' ', "");
+            for position in positions {
+                if position == view.entity.position {
+                    glyph = Glyph::new('@', COLOR_PLAYER);
+                    break;
+                }
+                if !view.is_visible(&position) && !view.entity.has_explored(level, &position) {
+                    continue;
+                }
+                glyph = match view.floor.get_cell(&position) {
+                    Cell::Exit => Glyph::new(Cell::Exit.as_char(), ""),
+                    _ if glyph.ch == Cell::Exit.as_char() => glyph,
+                    Cell::Wall if glyph.ch == ' ' => Glyph::new('▒', COLOR_EXPLORED),
+                    Cell::Wall => glyph,
+                    _ => Glyph::new('·', ""),
+                };
+            }
+            frame.put(x + offset + col, y + row, glyph.ch, glyph.color);
+        }
+    }
+}
@@ -1,9 +1,35 @@
 use rand::SeedableRng;
 use rand_pcg::Pcg32;
 use rogue_lib::{
-    cell::{Cell, Effect, InstantDamage, TurnBasedDamage},
-    entities::{Action, Direction, Entity, Immovable, Position},
-    floor::Floor,
+    cell::{Cell, Confusion, Effect, EffectColor, InstantDamage, TurnBasedDamage},
+    config::{ConfigError, ConfigPrefab, ConfigVictory, MIN_FLOOR_SIZE},
+    entities::{
+        Action, Behavior, Chaser, CombatStats, Direction, Entity, Growth, Immovable, Position,
+        Statistics,
+    },
+    event::{Event, EventKind},
+    floor::{Floor, FloorView, PathOptions, Stairs, SIGHT_RADIUS},
+    game::{GameOutcome, StableHasher},
+    generator::{BspRooms, CellularCaves, DrunkardWalk, FloorGenerator, Generator, RoomsAndMazes},
+    item::{Key, Potion, Weapon},
+    play_console_turn,
+    prefab::{Prefab, PrefabTile},
+    remote::{RemoteClient, Server, ServerMessage},
+    replay::{Replay, ReplayBehavior},
+    save::{self, SaveError, SaveFormat, SAVE_VERSION},
+    score::{HighScores, Score, HIGH_SCORES_SIZE},
+    simulation::{ExitSeeker, Simulation},
+    snapshot::{CellKind, FloorSnapshot, SNAPSHOT_VERSION},
+    status::{Blindness, Haste, Poison, Regeneration, Slow, Strength, Stun, Teleport},
+    tui::{self, Frame, LookMap, PANEL_WIDTH},
+    update_high_scores, ConsoleInput, ConsoleOptions, CONSOLE_SAVE_FILE,
+};
+use std::{
+    hash::Hasher,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 /*******************************************************/
@@ -19,6 +45,28 @@ fn get_basic_floor() -> Floor {
     Floor::new(0, rng, vec![], grid)
 }
 
+fn get_deterministic_config() -> rogue_lib::config::Config {
+    rogue_lib::config::Config {
+        game_seed: 42,
+        ..Default::default()
+    }
+}
+
+fn get_scripted_actions(turns: usize) -> Vec<Action> {
+    let directions = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+    (0..turns)
+        .map(|i| match i % 3 {
+            0 => Action::Attack(directions[(i / 3) % 4]),
+            _ => Action::Move(directions[(i / 7) % 4]),
+        })
+        .collect()
+}
+
 /*******************************************************/
 /* I tests iniziano da qui in poi                      */
 /*******************************************************/
@@ -68,6 +116,56 @@ fn test_cell_trait_effect_instant_damage() {
     assert_eq!(entity.get_health(), health);
 }
 
+/// Effetto senza nome e descrizione, per controllare quelli di base del trait.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct PlainEffect;
+#[typetag::serde]
+impl Effect for PlainEffect {
+    fn is_persistent(&self) -> bool {
+        false
+    }
+    fn apply_to(&self, _entity: &mut Entity, _floor: &mut Floor) {}
+}
+
+#[test]
+fn test_cell_trait_effect_descriptions() {
+    let effects: Vec<Box<dyn Effect>> = vec![
+        Box::new(InstantDamage(10)),
+        Box::new(InstantDamage(-10)),
+        Box::new(Confusion(3)),
+        Box::new(TurnBasedDamage::new(5, 2)),
+        Box::new(TurnBasedDamage::new(5, -2)),
+    ];
+    let names = effects.iter().map(|e| e.get_name()).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            "Instant damage",
+            "Instant healing",
+            "Confusion",
+            "Damage over time",
+            "Healing over time"
+        ]
+    );
+    assert_eq!(effects[0].get_description(), "Deals 10 damage once");
+    assert_eq!(effects[1].get_description(), "Heals 10 health once");
+    assert_eq!(
+        effects[4].get_description(),
+        "Heals 2 health per turn for 5 turns"
+    );
+
+    let glyphs = effects
+        .iter()
+        .map(|e| (e.as_char(), e.get_color()))
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(glyphs.len(), effects.len());
+    assert_eq!(effects[0].get_color(), EffectColor::Red);
+    assert_eq!(effects[1].get_color(), EffectColor::Green);
+
+    assert_eq!(PlainEffect.get_name(), "PlainEffect");
+    assert_eq!(PlainEffect.get_description(), "");
+}
+
 #[test]
 fn test_directions() {
     let mut dir = Direction::Up;
@@ -181,7 +279,8 @@ fn test_entity_basic_effects() {
     let entity = entity.update(&mut floor).unwrap();
     assert_eq!(entity.get_health_max(), 100);
     assert_eq!(entity.get_health(), 70);
-    let mut iter = entity.get_effects();
+    assert!(matches!(entity.get_effects().next(), None));
+    let mut iter = entity.get_statuses();
     assert!(matches!(iter.next(), Some(_)));
     assert!(matches!(iter.next(), None));
     std::mem::drop(iter);
@@ -189,13 +288,229 @@ fn test_entity_basic_effects() {
     let mut entity = entity.update(&mut floor).unwrap();
     assert_eq!(entity.get_health_max(), 100);
     assert_eq!(entity.get_health(), 60);
-    assert!(matches!(entity.get_effects().next(), None));
+    assert!(entity.get_statuses().next().is_none());
+    let damage_taken = &entity.get_statistics().damage_taken;
+    assert_eq!(damage_taken.get("TurnBasedDamage"), Some(&20));
+
+    entity.add_effect(Box::new(TurnBasedDamage::new(1, -10)));
+    let mut entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.get_health(), 70);
+    assert!(entity.get_statuses().next().is_none());
+
+    entity.position = Position(10, 10);
+    entity.add_effect(Box::new(Confusion(20)));
+    let mut entity = entity.update(&mut floor).unwrap();
+    let status = entity.get_statuses().next().unwrap();
+    assert_eq!(status.get_status().get_name(), "Confusion");
+    assert_eq!(status.get_remaining(), 20);
+    for _ in 0..21 {
+        entity = entity.update(&mut floor).unwrap();
+    }
+    assert!(entity.get_statuses().next().is_none());
+    assert_ne!(entity.position, Position(10, 10));
+
+    let legacy = r#"{"type":"Confusion","value":3}"#;
+    let effect: Box<dyn Effect> = serde_json::from_str(legacy).unwrap();
+    assert!(effect.is_persistent());
+    let legacy = r#"{"type":"TurnBasedDamage","time":3,"damage":1}"#;
+    let effect: Box<dyn Effect> = serde_json::from_str(legacy).unwrap();
+    assert_eq!(effect.get_name(), "Damage over time");
 
     entity.add_effect(Box::new(InstantDamage(100)));
     let entity = entity.update(&mut floor);
     assert!(matches!(entity, None));
 }
 
+#[test]
+fn test_entity_statuses() {
+    let mut floor = get_basic_floor();
+    let mut entity = get_basic_entity();
+    entity.position = Position(10, 10);
+    assert!(entity.get_statuses().next().is_none());
+
+    let poison = |duration| {
+        Box::new(Poison {
+            damage: 5,
+            duration,
+        })
+    };
+    let regeneration = |heal, duration| Box::new(Regeneration { heal, duration });
+    entity.add_status(poison(2), &mut floor);
+    entity.add_status(poison(3), &mut floor);
+    entity.add_status(regeneration(1, 2), &mut floor);
+    entity.add_status(regeneration(2, 1), &mut floor);
+    entity.add_status(Box::new(Stun { duration: 0 }), &mut floor);
+    let statuses = |entity: &Entity| {
+        entity
+            .get_statuses()
+            .map(|active| (active.get_status().get_name(), active.get_remaining()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        statuses(&entity),
+        vec![
+            ("Poison".to_string(), 2),
+            ("Poison".to_string(), 3),
+            ("Regeneration".to_string(), 2)
+        ]
+    );
+
+    let entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.get_health(), 92);
+    let entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.get_health(), 84);
+    assert_eq!(statuses(&entity), vec![("Poison".to_string(), 1)]);
+    let mut entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.get_health(), 79);
+    assert!(entity.get_statuses().next().is_none());
+    let damage = &entity.get_statistics().damage_taken;
+    assert_eq!(damage.get("Poison"), Some(&25));
+    let event = floor.get_events().last().unwrap();
+    assert_eq!(event.kind, EventKind::Damage("Poison".to_string(), 5));
+
+    entity.add_status(Box::new(Haste { duration: 2 }), &mut floor);
+    entity.add_status(Box::new(Haste { duration: 3 }), &mut floor);
+    entity.add_status(Box::new(Stun { duration: 2 }), &mut floor);
+    entity.add_status(Box::new(Stun { duration: 5 }), &mut floor);
+    assert_eq!(
+        statuses(&entity),
+        vec![("Haste".to_string(), 5), ("Stun".to_string(), 2)]
+    );
+    assert_eq!(entity.get_actions(), 0);
+
+    let mut entity = get_basic_entity();
+    assert_eq!(entity.get_actions(), 1);
+    assert_eq!(entity.get_attack(), 10);
+    assert_eq!(entity.get_sight_radius(), SIGHT_RADIUS);
+    entity.add_status(Box::new(Haste { duration: 1 }), &mut floor);
+    entity.add_status(
+        Box::new(Strength {
+            bonus: 5,
+            duration: 1,
+        }),
+        &mut floor,
+    );
+    entity.add_status(
+        Box::new(Blindness {
+            radius: 5,
+            duration: 1,
+        }),
+        &mut floor,
+    );
+    assert_eq!(entity.get_actions(), 2);
+    assert_eq!(entity.get_attack(), 15);
+    assert_eq!(entity.get_sight_radius(), SIGHT_RADIUS - 5);
+    let modifiers = entity.get_modifiers();
+    assert_eq!((modifiers.speed, modifiers.sight), (1, -5));
+
+    entity.add_status(
+        Box::new(Blindness {
+            radius: 100,
+            duration: 1,
+        }),
+        &mut floor,
+    );
+    assert_eq!(entity.get_sight_radius(), 1);
+    let view = floor.get_limited_view_floor(&entity);
+    assert!(view.is_visible(&Position(0, 1)));
+    assert!(!view.is_visible(&Position(0, 2)));
+
+    let json = serde_json::to_string(&entity).unwrap();
+    let loaded: Entity = serde_json::from_str(&json).unwrap();
+    assert_eq!(statuses(&loaded), statuses(&entity));
+    let entity = entity.update(&mut floor).unwrap();
+    assert!(entity.get_statuses().next().is_none());
+    assert_eq!(entity.get_attack(), 10);
+}
+
+/// Comportamento che conta quante volte gli viene mostrato il piano.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+struct CountingBehavior {
+    #[serde(skip)]
+    updates: std::rc::Rc<std::cell::Cell<usize>>,
+}
+#[typetag::serde]
+impl Behavior for CountingBehavior {
+    fn update(&mut self, _view: FloorView, _rng: &mut Pcg32) {
+        self.updates.set(self.updates.get() + 1);
+    }
+    fn get_next_action(&mut self, _entity: &Entity) -> Option<Action> {
+        Some(Action::DoNothing)
+    }
+}
+
+#[test]
+fn test_entity_status_actions() {
+    let mut floor = get_basic_floor();
+    let actions = vec![Action::Move(Direction::Up); 10];
+    let behavior = Box::new(ReplayBehavior::new(actions));
+    let mut entity = Entity::new("name".to_string(), 100, 10, behavior);
+    entity.position = Position(10, 10);
+
+    let effect: Box<dyn Effect> = Box::new(Haste { duration: 2 });
+    assert_eq!(effect.get_name(), "Haste");
+    assert!(!effect.is_persistent());
+    entity.add_effect(effect);
+    let (entity, actions) = entity.update_with_actions(&mut floor);
+    let entity = entity.alive().unwrap();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(entity.position, Position(10, 11));
+
+    let (entity, actions) = entity.update_with_actions(&mut floor);
+    let entity = entity.alive().unwrap();
+    assert_eq!(actions.len(), 2);
+    assert_eq!(entity.position, Position(10, 13));
+    let entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.position, Position(10, 15));
+    let mut entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.position, Position(10, 16));
+
+    entity.add_status(Box::new(Stun { duration: 1 }), &mut floor);
+    let (entity, actions) = entity.update_with_actions(&mut floor);
+    let mut entity = entity.alive().unwrap();
+    assert!(actions.is_empty());
+    assert_eq!(entity.position, Position(10, 16));
+    assert_eq!(entity.get_statistics().turns, 5);
+
+    let counting = CountingBehavior::default();
+    let updates = counting.updates.clone();
+    let mut other = Entity::new("other".to_string(), 100, 10, Box::new(counting));
+    other.add_status(Box::new(Stun { duration: 1 }), &mut floor);
+    let other = other.update(&mut floor).unwrap();
+    assert_eq!(updates.get(), 0);
+    let mut other = other.update(&mut floor).unwrap();
+    assert_eq!(updates.get(), 1);
+    other.add_status(Box::new(Slow { duration: 2 }), &mut floor);
+    let other = other.update(&mut floor).unwrap();
+    other.update(&mut floor).unwrap();
+    assert_eq!(updates.get(), 2);
+
+    entity.add_status(Box::new(Slow { duration: 4 }), &mut floor);
+    for _ in 0..4 {
+        entity = entity.update(&mut floor).unwrap();
+    }
+    assert_eq!(entity.position, Position(10, 18));
+    assert_eq!(entity.get_statistics().turns, 9);
+    let entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.position, Position(10, 19));
+
+    let mut entity = get_basic_entity();
+    entity.position = Position(10, 10);
+    let mut grid = vec![vec![Cell::Wall; 20]; 20];
+    grid[3][4] = Cell::Empty;
+    grid[10][10] = Cell::Special(Box::new(Teleport));
+    let mut floor = Floor::new(0, Pcg32::seed_from_u64(0), vec![], grid);
+    floor
+        .get_cell_mut(&Position(10, 10))
+        .entity_over(&mut entity);
+    assert!(matches!(
+        floor.get_cell(&Position(10, 10)),
+        Cell::Special(_)
+    ));
+    let entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.position, Position(3, 4));
+}
+
 #[test]
 fn test_entity_basic_action() {
     let mut floor = get_basic_floor();
@@ -280,6 +595,500 @@ fn test_floor_entrance_exit_player() {
     assert!(matches!(floor.get_player_at_exit(), Some(_)));
     assert!(matches!(floor.get_player_at_exit(), None));
     assert!(matches!(floor.get_cell(&Position(10, 10)), Cell::Exit));
+
+    let boss = Entity::new("boss".to_string(), 10, 0, Box::new(Immovable));
+    floor.add_boss(boss, vec![]);
+    floor.add_player_at(get_basic_entity(), Position(10, 10));
+    assert!(floor.is_exit_locked());
+    assert_eq!(
+        floor.get_players().next().unwrap().position,
+        Position(10, 10)
+    );
+    assert!(floor.get_player_at_exit().is_none());
+}
+
+#[test]
+fn test_floor_field_of_view() {
+    let mut floor = get_basic_floor();
+    for y in 0..20 {
+        *floor.get_cell_mut(&Position(10, y)) = Cell::Wall;
+    }
+
+    let visible = floor.get_visible_from(&Position(5, 5), 8);
+    assert!(visible.contains(&Position(5, 5)));
+    assert!(visible.contains(&Position(9, 5)));
+    assert!(visible.contains(&Position(10, 5)));
+    assert!(!visible.contains(&Position(11, 5)));
+    assert!(!visible.contains(&Position(5, 14)));
+
+    let mut entity = get_basic_entity();
+    entity.position = Position(5, 5);
+    let view = FloorView::new(&floor, &entity);
+    assert!(view.is_visible(&Position(9, 5)));
+    assert!(!view.is_visible(&Position(11, 5)));
+
+    let grid = view
+        .get_grid(10)
+        .flatten()
+        .map(|cell| (cell.position, cell.cell.is_some(), cell.visible))
+        .collect::<Vec<_>>();
+    assert!(grid.contains(&(Position(9, 5), true, true)));
+    assert!(grid.contains(&(Position(11, 5), false, false)));
+}
+
+#[test]
+fn test_entity_explored_memory() {
+    let floor = get_basic_floor();
+    let mut entity = get_basic_entity();
+    entity.position = Position(2, 2);
+    assert!(!entity.has_explored(0, &Position(2, 2)));
+
+    entity.explore(&floor);
+    assert!(entity.has_explored(0, &Position(2, 2)));
+    assert!(entity.has_explored(0, &Position(5, 5)));
+    assert!(!entity.has_explored(0, &Position(15, 15)));
+    assert!(!entity.has_explored(1, &Position(2, 2)));
+
+    entity.position = Position(15, 15);
+    let view = FloorView::new(&floor, &entity);
+    let cell = view
+        .get_grid(20)
+        .flatten()
+        .find(|cell| cell.position == Position(2, 2))
+        .unwrap();
+    assert!(cell.cell.is_some());
+    assert!(!cell.visible);
+
+    let json = serde_json::to_string(&entity).unwrap();
+    let entity: Entity = serde_json::from_str(&json).unwrap();
+    assert!(entity.has_explored(0, &Position(2, 2)));
+    assert!(!entity.has_explored(0, &Position(15, 15)));
+}
+
+#[test]
+fn test_entity_inventory() {
+    let mut floor = get_basic_floor();
+    let mut entity = get_basic_entity();
+    entity.position = Position(10, 10);
+    entity.apply_damage(50);
+
+    let potion = Potion::new("potion".to_string(), Box::new(InstantDamage(-20)));
+    *floor.get_cell_mut(&Position(10, 10)) = Cell::Item(Box::new(potion));
+    *floor.get_cell_mut(&Position(10, 11)) =
+        Cell::Item(Box::new(Weapon::new("sword".to_string(), 5)));
+    assert_eq!(entity.get_attack(), 10);
+
+    entity.buffer = Action::PickUp;
+    let mut entity = entity.update(&mut floor).unwrap();
+    assert!(matches!(floor.get_cell(&Position(10, 10)), Cell::Empty));
+    assert_eq!(entity.get_inventory().count(), 1);
+
+    entity.buffer = Action::Move(Direction::Up);
+    let mut entity = entity.update(&mut floor).unwrap();
+    assert!(matches!(floor.get_cell(&Position(10, 11)), Cell::Item(_)));
+    entity.buffer = Action::PickUp;
+    let mut entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.get_inventory().count(), 2);
+    assert_eq!(entity.get_attack(), 15);
+
+    entity.buffer = Action::Use(1);
+    let mut entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.get_inventory().count(), 2);
+
+    entity.buffer = Action::Use(0);
+    let mut entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.get_health(), 70);
+    let names = entity
+        .get_inventory()
+        .map(|i| i.get_name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["sword (+5)".to_string()]);
+
+    entity.buffer = Action::Drop(0);
+    let mut entity = entity.update(&mut floor).unwrap();
+    assert_eq!(entity.get_inventory().count(), 0);
+    assert_eq!(entity.get_attack(), 10);
+    assert_eq!(floor.get_cell(&Position(10, 11)).as_char(), '/');
+
+    for i in 0..rogue_lib::entities::INVENTORY_SIZE {
+        assert!(entity.add_item(Box::new(Key(i.to_string()))).is_ok());
+    }
+    assert!(entity.add_item(Box::new(Key("last".to_string()))).is_err());
+    entity.buffer = Action::PickUp;
+    let entity = entity.update(&mut floor).unwrap();
+    assert!(matches!(floor.get_cell(&Position(10, 11)), Cell::Item(_)));
+
+    let json = serde_json::to_string(&entity).unwrap();
+    let entity: Entity = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        entity.get_inventory().count(),
+        rogue_lib::entities::INVENTORY_SIZE
+    );
+}
+
+#[test]
+fn test_floor_find_path() {
+    let mut floor = get_basic_floor();
+    for y in 0..19 {
+        *floor.get_cell_mut(&Position(10, y)) = Cell::Wall;
+    }
+
+    let options = PathOptions::default();
+    let path = floor.find_path(&Position(5, 5), &Position(15, 5), options);
+    let path = path.unwrap();
+    assert_eq!(path.len(), 5 + 14 + 14 + 5);
+    assert_eq!(path.last(), Some(&Position(15, 5)));
+    assert!(path.contains(&Position(10, 19)));
+
+    let path = floor.find_path(&Position(5, 5), &Position(5, 5), options);
+    assert_eq!(path, Some(vec![]));
+
+    *floor.get_cell_mut(&Position(10, 19)) = Cell::Special(Box::new(InstantDamage(10)));
+    let path = floor.find_path(&Position(5, 5), &Position(15, 5), options);
+    assert!(path.is_some());
+
+    let options = PathOptions {
+        avoid_special: true,
+        avoid_entities: false,
+    };
+    let path = floor.find_path(&Position(5, 5), &Position(15, 5), options);
+    assert!(path.is_none());
+
+    *floor.get_cell_mut(&Position(10, 19)) = Cell::Wall;
+    let path = floor.find_path(&Position(5, 5), &Position(15, 5), PathOptions::default());
+    assert!(path.is_none());
+}
+
+#[test]
+fn test_entity_chaser() {
+    let rng = Pcg32::seed_from_u64(0);
+    let mut chaser = Entity::new("chaser".to_string(), 100, 10, Box::new(Chaser::new(5)));
+    chaser.position = Position(10, 14);
+    let mut floor = Floor::new(0, rng, vec![chaser], vec![vec![Cell::Empty; 20]; 20]);
+    *floor.get_cell_mut(&Position(10, 10)) = Cell::Entrance;
+    floor.add_player(get_basic_entity());
+
+    floor.update_entities();
+    let chaser = floor.get_entity_at(&Position(10, 13));
+    assert!(chaser.is_some());
+
+    floor.update_entities();
+    floor.update_entities();
+    let chaser = floor.get_entity_at(&Position(10, 11));
+    assert!(chaser.is_some());
+
+    floor.update_entities();
+    let player = floor.get_players().next().unwrap();
+    assert_eq!(player.position, Position(10, 10));
+    assert_eq!(player.get_health(), 90);
+    assert!(floor.get_entity_at(&Position(10, 11)).is_some());
+}
+
+#[test]
+fn test_entity_combat_stats() {
+    let growth = Growth {
+        experience: 100,
+        multiplier: 2.0,
+        reward: 150,
+        ..Default::default()
+    };
+    let stats = CombatStats::new(10, 4, 100);
+    let behavior = Box::new(ReplayBehavior::new(vec![Action::Attack(Direction::Up); 5]));
+    let mut player = Entity::new_with("player".to_string(), 100, stats, growth.clone(), behavior);
+    player.position = Position(10, 10);
+    assert_eq!(player.get_stats().level, 1);
+    assert_eq!(player.get_damage_from(10), 6);
+    assert_eq!(player.get_damage_from(2), 1);
+
+    let stats = CombatStats::new(0, 4, 0);
+    let mut enemy = Entity::new_with("enemy".to_string(), 12, stats, growth, Box::new(Immovable));
+    enemy.position = Position(10, 11);
+
+    let rng = Pcg32::seed_from_u64(0);
+    let mut floor = Floor::new(0, rng, vec![enemy], vec![vec![Cell::Empty; 20]; 20]);
+    *floor.get_cell_mut(&Position(10, 10)) = Cell::Entrance;
+    floor.add_player(player);
+
+    floor.update_players();
+    let enemy = floor.get_entity_at(&Position(10, 11)).unwrap();
+    assert_eq!(enemy.get_health(), 6);
+    let player = floor.get_players().next().unwrap();
+    assert_eq!(player.get_health(), 100);
+
+    floor.update_players();
+    floor.update_entities();
+    assert!(floor.get_entity_at(&Position(10, 11)).is_none());
+    let player = floor.get_players().next().unwrap();
+    let stats = player.get_stats();
+    assert_eq!(player.get_statistics().kills, 1);
+    assert_eq!(stats.level, 2);
+    assert_eq!(stats.experience, 50);
+    assert_eq!(stats.attack, 12);
+    assert_eq!(stats.defense, 5);
+    assert_eq!(player.get_health_max(), 110);
+    assert_eq!(player.get_growth().experience_for(stats.level), 200);
+
+    let mut player = player.clone();
+    player.add_experience(150 + 400);
+    assert_eq!(player.get_stats().level, 4);
+    assert_eq!(player.get_stats().experience, 0);
+    assert_eq!(player.get_stats().accuracy, 100);
+}
+
+#[test]
+fn test_floor_events() {
+    let actions = vec![
+        Action::Attack(Direction::Up),
+        Action::Move(Direction::Right),
+    ];
+    let player = Entity::new(
+        "player".to_string(),
+        100,
+        10,
+        Box::new(ReplayBehavior::new(actions)),
+    );
+    let mut enemy = Entity::new("enemy".to_string(), 10, 0, Box::new(Immovable));
+    enemy.position = Position(10, 11);
+
+    let rng = Pcg32::seed_from_u64(0);
+    let mut floor = Floor::new(3, rng, vec![enemy], vec![vec![Cell::Empty; 20]; 20]);
+    *floor.get_cell_mut(&Position(10, 10)) = Cell::Entrance;
+    *floor.get_cell_mut(&Position(11, 10)) = Cell::Special(Box::new(InstantDamage(5)));
+    floor.add_player(player);
+
+    floor.set_turn(1);
+    floor.update_players();
+    floor.update_entities();
+    floor.set_turn(2);
+    floor.update_players();
+
+    let events = floor.get_events().cloned().collect::<Vec<_>>();
+    let player = Some("player".to_string());
+    let expected = vec![
+        Event::new(0, None, "player".to_string(), EventKind::FloorChange(3)).at(Position(10, 10)),
+        Event::new(
+            1,
+            player.clone(),
+            "enemy".to_string(),
+            EventKind::Attack(10),
+        )
+        .at(Position(10, 11)),
+        Event::new(1, player, "enemy".to_string(), EventKind::Death).at(Position(10, 11)),
+        Event::new(
+            2,
+            None,
+            "player".to_string(),
+            EventKind::Effect("InstantDamage".to_string()),
+        )
+        .at(Position(11, 10)),
+        Event::new(
+            2,
+            None,
+            "player".to_string(),
+            EventKind::Damage("InstantDamage".to_string(), 5),
+        )
+        .at(Position(11, 10)),
+    ];
+    assert_eq!(events, expected);
+    assert_eq!(
+        events[1].to_string(),
+        "[   1] player hits enemy for 10 damage"
+    );
+    assert_eq!(
+        events[4].to_string(),
+        "[   2] player takes 5 damage from InstantDamage"
+    );
+
+    let mut entity = get_basic_entity();
+    entity.position = Position(12, 10);
+    let view = FloorView::new(&floor, &entity);
+    assert_eq!(view.get_events(2).count(), 2);
+    assert!(view.get_events(0).all(|event| event.involves("player")));
+
+    let mut grid = vec![vec![Cell::Empty; 20]; 20];
+    grid[5] = vec![Cell::Wall; 20];
+    let mut floor = Floor::new(0, Pcg32::seed_from_u64(0), vec![], grid);
+    let kind = EventKind::Damage("Poison".to_string(), 1);
+    floor.add_event(None, "enemy".to_string(), kind.clone(), Position(1, 1));
+    floor.add_event(None, "enemy".to_string(), kind.clone(), Position(10, 1));
+    floor.add_event(None, "name".to_string(), kind, Position(10, 10));
+    entity.position = Position(1, 2);
+    let view = FloorView::new(&floor, &entity);
+    let positions = view
+        .get_events(0)
+        .map(|event| event.position.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(positions, vec![Position(1, 1), Position(10, 10)]);
+}
+
+#[test]
+fn test_floor_stairs() {
+    let actions = vec![Action::Move(Direction::Up), Action::Move(Direction::Down)];
+    let player = Entity::new(
+        "player".to_string(),
+        100,
+        10,
+        Box::new(ReplayBehavior::new(actions)),
+    );
+    let mut follower = Entity::new("follower".to_string(), 10, 0, Box::new(Immovable));
+    follower.position = Position(11, 10);
+    follower.follow_stairs = true;
+    let mut other = Entity::new("other".to_string(), 10, 0, Box::new(Immovable));
+    other.position = Position(9, 10);
+    let entities = vec![follower, other];
+
+    let rng = Pcg32::seed_from_u64(0);
+    let mut floor = Floor::new(1, rng, entities, vec![vec![Cell::Empty; 20]; 20]);
+    *floor.get_cell_mut(&Position(10, 10)) = Cell::Entrance;
+    *floor.get_cell_mut(&Position(10, 11)) = Cell::Exit;
+    floor.add_player(player);
+    assert_eq!(floor.get_stairs_at(&Position(10, 10)), Some(Stairs::Up));
+    assert_eq!(floor.get_stairs_at(&Position(10, 11)), Some(Stairs::Down));
+    assert_eq!(floor.get_stairs_at(&Position(10, 12)), None);
+
+    floor.update_players();
+    let transition = floor.take_leaving().pop().unwrap();
+    assert_eq!(transition.stairs, Stairs::Down);
+    assert_eq!(transition.player.position, Position(10, 11));
+    assert!(transition.followers.is_empty());
+    assert!(!floor.has_players());
+
+    floor.add_player_at(transition.player, Position(10, 11));
+    floor.update_players();
+    let transition = floor.take_leaving().pop().unwrap();
+    assert_eq!(transition.stairs, Stairs::Up);
+    assert_eq!(transition.followers.len(), 1);
+    assert_eq!(transition.followers[0].get_name(), "follower");
+    assert_eq!(floor.get_all_entities().count(), 1);
+
+    let mut floor = Floor::new(
+        0,
+        Pcg32::seed_from_u64(0),
+        vec![],
+        vec![vec![Cell::Empty; 20]; 20],
+    );
+    *floor.get_cell_mut(&Position(10, 10)) = Cell::Entrance;
+    assert_eq!(floor.get_stairs_at(&Position(10, 10)), None);
+    floor.add_entity_near(transition.followers[0].clone(), &Position(10, 10));
+    let follower = floor.get_all_entities().next().unwrap();
+    assert_eq!(follower.position.distance(&Position(10, 10)), 1);
+}
+
+#[test]
+fn test_game_stairs() {
+    let config = rogue_lib::config::Config {
+        entities_total: 0,
+        effects_total: 0,
+        ..get_deterministic_config()
+    };
+    let floor = rogue_lib::game::Dungeon::new_with(config.clone())
+        .get_floor(0)
+        .clone();
+    let exit = floor.get_exit().unwrap();
+    let mut position = floor.get_entrance();
+    let mut actions = vec![];
+    for next in floor
+        .find_path(&position, &exit, PathOptions::default())
+        .unwrap()
+    {
+        actions.push(Action::Move(Direction::towards(&position, &next)));
+        position = next;
+    }
+    let turns = actions.len();
+
+    let mut game = rogue_lib::game::Dungeon::new_with(config.clone());
+    let behavior = Box::new(ReplayBehavior::new(actions.clone()));
+    game.add_player("Player".to_string(), behavior);
+    (0..turns).for_each(|_| game.compute_turn());
+    let player = game.get_players().next().unwrap();
+    let entrance = game.get_floor(1).get_entrance();
+    assert_eq!(player.position, entrance);
+    assert_eq!(player.get_statistics().deepest_floor, 1);
+
+    let direction = Direction::all()
+        .into_iter()
+        .find(|direction| {
+            let mut next = entrance;
+            direction.move_from(&mut next);
+            matches!(game.get_floor(1).get_cell(&next), Cell::Empty)
+        })
+        .unwrap();
+    let mut back = direction;
+    back.invert();
+    actions.extend([
+        Action::Move(direction),
+        Action::Move(back),
+        Action::DoNothing,
+    ]);
+
+    let mut game = rogue_lib::game::Dungeon::new_with(config);
+    game.add_player("Player".to_string(), Box::new(ReplayBehavior::new(actions)));
+    (0..turns + 2).for_each(|_| game.compute_turn());
+    assert!(!game.get_floor(1).has_players());
+    assert!(game.get_floor(0).has_players());
+    let player = game.get_players().next().unwrap();
+    assert_eq!(player.position, exit);
+
+    game.compute_turn();
+    assert!(game.get_floor(0).has_players());
+}
+
+#[test]
+fn test_floor_snapshot() {
+    let mut enemy = Entity::new("enemy".to_string(), 10, 0, Box::new(Immovable));
+    enemy.position = Position(12, 10);
+    let mut far = Entity::new("far".to_string(), 10, 0, Box::new(Immovable));
+    far.position = Position(18, 10);
+
+    let rng = Pcg32::seed_from_u64(0);
+    let mut floor = Floor::new(2, rng, vec![enemy, far], vec![vec![Cell::Empty; 20]; 20]);
+    *floor.get_cell_mut(&Position(10, 10)) = Cell::Entrance;
+    *floor.get_cell_mut(&Position(10, 12)) = Cell::Wall;
+    *floor.get_cell_mut(&Position(9, 10)) = Cell::Special(Box::new(InstantDamage(5)));
+    let potion = Potion::new("Potion".to_string(), Box::new(InstantDamage(-5)));
+    *floor.get_cell_mut(&Position(11, 11)) = Cell::Item(Box::new(potion));
+
+    let mut player = get_basic_entity();
+    player.add_effect(Box::new(TurnBasedDamage::new(3, 1)));
+    let _ = player.add_item(Box::new(Weapon::new("Sword".to_string(), 5)));
+    floor.add_player(player);
+    floor.add_player(get_basic_entity());
+
+    let entity = floor.get_players().next().unwrap();
+    let snapshot = FloorView::new(&floor, entity).snapshot(3);
+    assert_eq!(snapshot.version, SNAPSHOT_VERSION);
+    assert!(snapshot.is_compatible());
+    assert_eq!(snapshot.level, 2);
+    assert_eq!(snapshot.radius, 3);
+    assert_eq!(snapshot.entity.name, "name");
+    assert_eq!(snapshot.entity.position, Position(10, 10));
+    assert_eq!(snapshot.entity.inventory, vec!["Sword (+5)"]);
+    assert_eq!(snapshot.entity.effects, vec!["Damage over time"]);
+
+    let kind = |x, y| {
+        snapshot
+            .get_cell(&Position(x, y))
+            .map(|cell| cell.kind.clone())
+    };
+    assert_eq!(kind(10, 10), Some(CellKind::Entrance));
+    assert_eq!(kind(10, 12), Some(CellKind::Wall));
+    assert_eq!(
+        kind(9, 10),
+        Some(CellKind::Special("Instant damage".to_string()))
+    );
+    assert_eq!(kind(11, 11), Some(CellKind::Item("Potion".to_string())));
+    assert_eq!(kind(18, 10), None);
+    assert!(snapshot.cells.iter().all(|cell| cell.visible));
+
+    assert_eq!(snapshot.entities.len(), 2);
+    assert!(!snapshot.get_entity_at(&Position(12, 10)).unwrap().player);
+    let other = snapshot.entities.iter().find(|e| e.player).unwrap();
+    assert_eq!(other.position.distance(&Position(10, 10)), 1);
+    assert!(snapshot.get_entity_at(&Position(18, 10)).is_none());
+
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let loaded: FloorSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, snapshot);
 }
 
 #[test]
@@ -342,6 +1151,779 @@ fn test_game_initial_config() {
     assert_eq!(entrance, player.unwrap().position);
 }
 
+#[test]
+fn test_config_validate() {
+    let config = rogue_lib::config::Config::default();
+    assert_eq!(config.validate(), Ok(()));
+    assert_eq!(get_deterministic_config().validate(), Ok(()));
+
+    let mut config = rogue_lib::config::Config::default();
+    config.maze_generation.floor_size = 10..20;
+    config.maze_generation.room_size = 5..11;
+    config.maze_generation.straight_percentage = 150;
+    config.effects.clear();
+    config.entities.clear();
+    config.player_stats.health = 0;
+    config.bosses[0].minions[0].health = -5;
+
+    let errors = config.validate().unwrap_err();
+    assert_eq!(
+        errors,
+        vec![
+            ConfigError::FloorTooSmall {
+                floor: 10,
+                room: 10
+            },
+            ConfigError::StraightPercentage(150),
+            ConfigError::EmptyList("effects"),
+            ConfigError::EmptyList("entities"),
+            ConfigError::NoHealth("player".to_string()),
+            ConfigError::NoHealth("Guard".to_string()),
+        ]
+    );
+    assert_eq!(
+        errors[2].to_string(),
+        "effects must not be empty when effects_total is greater than 0"
+    );
+
+    config = rogue_lib::config::Config::default();
+    config.maze_generation.floor_size = 2..4;
+    config.maze_generation.room_size = 1..2;
+    let errors = config.validate().unwrap_err();
+    assert_eq!(errors, vec![ConfigError::FloorBelowMinimum(2)]);
+    assert_eq!(
+        errors[0].to_string(),
+        format!("floor_size must start from at least {MIN_FLOOR_SIZE} (found 2)")
+    );
+
+    config = rogue_lib::config::Config::default();
+    config.maze_generation.room_size = 5..5;
+    config.effects_total = 0;
+    config.effects.clear();
+    let errors = config.validate().unwrap_err();
+    assert_eq!(errors, vec![ConfigError::EmptyRange("room_size")]);
+
+    config = get_deterministic_config();
+    config
+        .items
+        .iter_mut()
+        .for_each(|item| item.floors = 2..255);
+    let errors = config.validate().unwrap_err();
+    let uncovered = ConfigError::UncoveredFloor {
+        list: "items",
+        floor: 0,
+    };
+    assert_eq!(errors, vec![uncovered]);
+    assert_eq!(
+        errors[0].to_string(),
+        "no entry of items can appear on floor 0"
+    );
+
+    let floor = Generator::new(0, 0, &config).build_floor();
+    let has_items = (0..floor.get_size())
+        .flat_map(|x| (0..floor.get_size()).map(move |y| Position(x, y)))
+        .any(|pos| matches!(floor.get_cell(&pos), Cell::Item(_)));
+    assert!(!has_items);
+    config.items_total = 0;
+    assert_eq!(config.validate(), Ok(()));
+}
+
+#[test]
+fn test_console_options() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+    let options = ConsoleOptions::from_args(args(&["--seed", "42", "--name", "Hero"])).unwrap();
+    assert_eq!(options.player, "Hero");
+    assert_eq!(options.config.game_seed, 42);
+    assert_eq!(options.load, None);
+    assert_eq!(options.save, Some(CONSOLE_SAVE_FILE.to_string()));
+    assert_eq!(options.format, SaveFormat::Json);
+    let options = ConsoleOptions::from_args(args(&["--no-save"])).unwrap();
+    assert_eq!(options.save, None);
+
+    let options =
+        ConsoleOptions::from_args(args(&["--load", "game.sav", "--save", "out", "--binary"]))
+            .unwrap();
+    assert_eq!(options.load, Some("game.sav".to_string()));
+    assert_eq!(options.save, Some("out".to_string()));
+    assert_eq!(options.format, SaveFormat::Binary);
+    let options = ConsoleOptions::from_args(args(&["--load", "game.sav"])).unwrap();
+    assert_eq!(options.save, Some("game.sav".to_string()));
+    let options = ConsoleOptions::from_args(args(&["--load", "game.sav", "--no-save"])).unwrap();
+    assert_eq!(options.save, None);
+
+    let mut config = get_deterministic_config();
+    config.maze_generation.straight_percentage = 101;
+    let file = std::env::temp_dir().join("rogue_lib_test_config.json");
+    let file = file.to_str().unwrap();
+    std::fs::write(file, serde_json::to_string(&config).unwrap()).unwrap();
+    let error = ConsoleOptions::from_args(args(&["--config", file])).unwrap_err();
+    assert!(error.contains("straight_percentage"));
+
+    config.maze_generation.straight_percentage = 90;
+    std::fs::write(file, serde_json::to_string(&config).unwrap()).unwrap();
+    let options = ConsoleOptions::from_args(args(&["--config", file])).unwrap();
+    assert_eq!(options.config.game_seed, 42);
+    let options = ConsoleOptions::from_args(args(&["--config", file, "--seed", "7"])).unwrap();
+    assert_eq!(options.config.game_seed, 7);
+
+    assert!(ConsoleOptions::from_args(args(&["--seed", "abc"])).is_err());
+    assert!(ConsoleOptions::from_args(args(&["--name"])).is_err());
+    assert!(ConsoleOptions::from_args(args(&["--unknown"])).is_err());
+    assert!(ConsoleOptions::from_args(args(&["--config", "missing.json"])).is_err());
+}
+
+#[test]
+fn test_console_play_turn() {
+    let config = rogue_lib::config::Config {
+        entities_total: 0,
+        effects_total: 0,
+        ..get_deterministic_config()
+    };
+    let mut game = rogue_lib::game::Dungeon::new_with(config);
+    let floor = game.get_floor(0).clone();
+    let exit = floor.get_exit().unwrap();
+    let mut position = floor.get_entrance();
+    let mut actions = vec![];
+    for next in floor
+        .find_path(&position, &exit, PathOptions::default())
+        .unwrap()
+    {
+        actions.push(Action::Move(Direction::towards(&position, &next)));
+        position = next;
+    }
+    let turns = actions.len();
+    actions.extend(vec![Action::DoNothing; 3]);
+    game.add_player("Player".to_string(), Box::new(ReplayBehavior::new(actions)));
+    game.add_player("Quitter".to_string(), Box::new(ReplayBehavior::new(vec![])));
+
+    let file = std::env::temp_dir().join("rogue_lib_test_console_turn.json");
+    let file = file.to_str().unwrap();
+    let _ = std::fs::remove_file(file);
+    let save_request = Arc::new(AtomicBool::new(false));
+    let format = SaveFormat::Json;
+    for _ in 1..turns {
+        assert!(play_console_turn(
+            &mut game,
+            &save_request,
+            Some(file),
+            format
+        ));
+        assert!(!std::path::Path::new(file).exists());
+    }
+    assert_eq!(game.get_outcome("Quitter"), Some(GameOutcome::Quit));
+
+    assert!(play_console_turn(
+        &mut game,
+        &save_request,
+        Some(file),
+        format
+    ));
+    assert_eq!(game.get_floor_changes().collect::<Vec<_>>(), vec!["Player"]);
+    let loaded = rogue_lib::game::Dungeon::load(file).unwrap();
+    assert_eq!(loaded.get_player_level("Player"), Some(1));
+    std::fs::remove_file(file).unwrap();
+
+    assert!(play_console_turn(
+        &mut game,
+        &save_request,
+        Some(file),
+        format
+    ));
+    assert!(!std::path::Path::new(file).exists());
+
+    let turn = game.get_turn();
+    let checksum = game.checksum();
+    save_request.store(true, Ordering::Relaxed);
+    assert!(!play_console_turn(
+        &mut game,
+        &save_request,
+        Some(file),
+        format
+    ));
+    assert!(!save_request.load(Ordering::Relaxed));
+    assert_eq!(game.get_turn(), turn);
+    assert_eq!(game.checksum(), checksum);
+    let loaded = rogue_lib::game::Dungeon::load(file).unwrap();
+    assert_eq!(loaded.get_turn(), turn);
+    assert_eq!(loaded.checksum(), checksum);
+    std::fs::remove_file(file).unwrap();
+
+    save_request.store(true, Ordering::Relaxed);
+    assert!(!play_console_turn(&mut game, &save_request, None, format));
+    assert!(!std::path::Path::new(file).exists());
+}
+
+#[test]
+fn test_game_multiplayer() {
+    let config = rogue_lib::config::Config {
+        entities_total: 0,
+        effects_total: 0,
+        ..get_deterministic_config()
+    };
+    let mut game = rogue_lib::game::Dungeon::new_with(config);
+    let actions = vec![Action::DoNothing; 3];
+    game.add_player("A".to_string(), Box::new(ReplayBehavior::new(actions)));
+    game.add_player("B".to_string(), Box::new(Immovable));
+    game.add_player("C".to_string(), Box::new(Immovable));
+
+    let entrance = game.get_floor(0).get_entrance();
+    let positions = game.get_players().map(|p| p.position).collect::<Vec<_>>();
+    assert_eq!(positions[0], entrance);
+    assert!(positions.iter().all(|pos| pos.distance(&entrance) <= 2));
+    assert!((0..3).all(|i| (0..i).all(|j| positions[i] != positions[j])));
+
+    let mut first = vec![];
+    for _ in 0..5 {
+        game.compute_turn();
+        first.push(game.get_players().next().unwrap().get_name().clone());
+    }
+    assert_eq!(first, vec!["B", "C", "A", "C", "B"]);
+
+    assert!(game.has_players());
+    assert!(!game.is_in_game("A"));
+    assert!(game.is_in_game("B"));
+    assert!(game.get_player("A").is_some());
+    assert!(game.get_player("D").is_none());
+    assert_eq!(game.get_departed().count(), 1);
+    assert_eq!(game.get_player("A").unwrap().get_statistics().turns, 3);
+}
+
+#[test]
+fn test_game_victory() {
+    let mut config = rogue_lib::config::Config {
+        entities_total: 0,
+        effects_total: 0,
+        ..get_deterministic_config()
+    };
+    config.victory = ConfigVictory {
+        max_turns: Some(4),
+        ..Default::default()
+    };
+
+    let mut statistics = Statistics::default();
+    assert!(!config.victory.is_reached(&statistics));
+    statistics.turns = 4;
+    assert!(config.victory.is_reached(&statistics));
+    assert!(ConfigVictory {
+        score: Some(statistics.get_score()),
+        ..Default::default()
+    }
+    .is_reached(&statistics));
+    assert!(!ConfigVictory::default().is_reached(&statistics));
+
+    let mut game = rogue_lib::game::Dungeon::new_with(config.clone());
+    let actions = vec![Action::DoNothing; 2];
+    game.add_player("A".to_string(), Box::new(ReplayBehavior::new(actions)));
+    game.add_player("B".to_string(), Box::new(Immovable));
+    for _ in 0..3 {
+        game.compute_turn();
+    }
+    assert_eq!(game.get_outcome("A"), Some(GameOutcome::Quit));
+    assert_eq!(game.get_outcome("B"), None);
+
+    game.compute_turn();
+    assert!(!game.has_players());
+    assert_eq!(game.get_outcome("B"), Some(GameOutcome::Victory));
+    assert_eq!(game.get_player("B").unwrap().get_statistics().turns, 4);
+    let position = game.get_player("B").unwrap().position;
+    let victory = Event::new(3, None, "B".to_string(), EventKind::Victory).at(position);
+    assert_eq!(game.get_floor(0).get_events().last(), Some(&victory));
+    assert_eq!(game.get_outcomes().count(), 2);
+
+    let scores = game.get_scores().collect::<Vec<_>>();
+    assert_eq!(scores.len(), 1);
+    assert_eq!(scores[0].name, "B");
+    assert_eq!(scores[0].outcome, GameOutcome::Victory);
+    assert_eq!(scores[0].turns, 4);
+    assert_eq!(scores[0].score, 4);
+}
+
+#[test]
+#[should_panic(expected = "Player names must be unique!")]
+fn test_game_duplicate_player() {
+    let mut game = rogue_lib::game::Dungeon::new_with(get_deterministic_config());
+    game.add_player("A".to_string(), Box::new(ReplayBehavior::new(vec![])));
+    game.compute_turn();
+    assert_eq!(game.get_outcome("A"), Some(GameOutcome::Quit));
+    game.add_player("A".to_string(), Box::new(Immovable));
+}
+
+#[test]
+fn test_score_high_scores() {
+    let config = get_deterministic_config();
+    let key = HighScores::key(&config);
+    assert_eq!(key, HighScores::key(&config.clone()));
+    let other_seed = rogue_lib::config::Config {
+        game_seed: 1,
+        ..config.clone()
+    };
+    let other_rules = rogue_lib::config::Config {
+        entities_total: 0,
+        ..config.clone()
+    };
+    assert_ne!(key, HighScores::key(&other_seed));
+    assert_ne!(key, HighScores::key(&other_rules));
+
+    let mut player = get_basic_entity();
+    player.get_statistics_mut().deepest_floor = 2;
+    player.get_statistics_mut().kills = 3;
+    player.get_statistics_mut().items = 1;
+    player.get_statistics_mut().turns = 50;
+    let score = Score::new(&player, GameOutcome::Defeat);
+    assert_eq!(score.score, 200 + 30 + 5 + 50);
+
+    let mut high_scores = HighScores::new();
+    for points in [10, 30, 20, 30] {
+        let score = Score {
+            score: points,
+            ..score.clone()
+        };
+        high_scores.add(key.clone(), score);
+    }
+    let points = high_scores.get(&key).iter().map(|s| s.score);
+    assert_eq!(points.collect::<Vec<_>>(), vec![30, 30, 20, 10]);
+    assert!(high_scores.get("missing").is_empty());
+
+    for _ in 0..HIGH_SCORES_SIZE {
+        high_scores.add(key.clone(), score.clone());
+    }
+    assert_eq!(high_scores.get(&key).len(), HIGH_SCORES_SIZE);
+    assert_eq!(
+        high_scores.add(key.clone(), Score { score: 0, ..score }),
+        None
+    );
+
+    let file = std::env::temp_dir().join("rogue_lib_test_highscores.json");
+    let file = file.to_str().unwrap();
+    high_scores.save(file).unwrap();
+    let loaded = HighScores::load(file).unwrap();
+    assert_eq!(loaded.get(&key), high_scores.get(&key));
+    let missing = std::env::temp_dir().join("rogue_lib_test_missing.json");
+    let missing = HighScores::load(missing.to_str().unwrap()).unwrap();
+    assert!(missing.get(&key).is_empty());
+}
+
+#[test]
+fn test_score_update_high_scores() {
+    let mut game = rogue_lib::game::Dungeon::new_with(get_deterministic_config());
+    game.add_player("Player".to_string(), Box::new(ReplayBehavior::new(vec![])));
+    let key = HighScores::key(game.get_config());
+
+    let file = std::env::temp_dir().join("rogue_lib_test_update_highscores.json");
+    let file = file.to_str().unwrap();
+    let _ = std::fs::remove_file(file);
+    let high_scores = update_high_scores(&game, &key, file).unwrap();
+    assert!(high_scores.get(&key).is_empty());
+    assert!(HighScores::load(file).is_ok());
+    assert!(!std::path::Path::new(&format!("{file}.tmp")).exists());
+
+    std::fs::write(file, "not a high score table").unwrap();
+    assert!(update_high_scores(&game, &key, file).is_none());
+    let content = std::fs::read_to_string(file).unwrap();
+    assert_eq!(content, "not a high score table");
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+fn test_remote_loopback() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let quitter = std::thread::spawn(move || {
+        let mut quitter = RemoteClient::connect(address, "Quitter".to_string()).unwrap();
+        for turn in 0..3 {
+            let ServerMessage::View(_, view) = quitter.receive().unwrap() else {
+                panic!("Expected a view of the floor");
+            };
+            assert_eq!(view.entity.name, "Quitter");
+            assert_eq!(view.turn, turn);
+            assert_eq!(view.level, 0);
+            assert!(view.is_compatible());
+            assert!(!view.cells.is_empty());
+            quitter.send_action(Action::DoNothing).unwrap();
+        }
+    });
+    let client = std::thread::spawn(move || {
+        let mut client = RemoteClient::connect(address, "Client".to_string()).unwrap();
+        let mut views = vec![];
+        while let Ok(ServerMessage::View(_, view)) = client.receive() {
+            client.send_action(Action::Move(Direction::Up)).unwrap();
+            views.push(view);
+        }
+        views
+    });
+
+    let mut server = Server::new(get_deterministic_config());
+    server.players = 2;
+    server.max_turns = 20;
+    let dungeon = server.run(&listener).unwrap();
+    quitter.join().unwrap();
+    let views = client.join().unwrap();
+
+    assert_eq!(dungeon.get_turn(), 20);
+    assert_eq!(views.len(), 20);
+    assert!(views.iter().all(|view| view.entity.name == "Client"));
+    assert!(views[0].entities.iter().any(|e| e.name == "Quitter"));
+    assert!(!dungeon.is_in_game("Quitter"));
+    assert!(dungeon.is_in_game("Client"));
+    let quitter = dungeon.get_player("Quitter").unwrap();
+    assert_eq!(quitter.get_statistics().turns, 3);
+}
+
+#[test]
+fn test_remote_timeouts() {
+    use std::io::{BufRead, BufReader, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let clients = std::thread::spawn(move || {
+        let mut bad = std::net::TcpStream::connect(address).unwrap();
+        bad.write_all(b"not a message\n").unwrap();
+        drop(bad);
+
+        let mut slow = std::net::TcpStream::connect(address).unwrap();
+        slow.write_all(b"{\"Join\":\"A\"}\n").unwrap();
+        let mut duplicate = RemoteClient::connect(address, "A".to_string()).unwrap();
+        let rejected = duplicate.receive().unwrap();
+        assert!(matches!(rejected, ServerMessage::Rejected(_)));
+
+        let fast = std::thread::spawn(move || {
+            let mut client = RemoteClient::connect(address, "B".to_string()).unwrap();
+            while let Ok(ServerMessage::View(_, _)) = client.receive() {
+                client.send_action(Action::DoNothing).unwrap();
+            }
+        });
+
+        let wait = std::time::Duration::from_millis(300);
+        let mut reader = BufReader::new(slow.try_clone().unwrap());
+        let mut next_request = || {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            match serde_json::from_str(&line) {
+                Ok(ServerMessage::View(request, _)) => Some(request),
+                _ => None,
+            }
+        };
+        let request = next_request().unwrap();
+        std::thread::sleep(wait);
+        let late = format!("{{\"Action\":[{request},{{\"Move\":\"Up\"}}]}}\n");
+        slow.write_all(late.as_bytes()).unwrap();
+
+        let request = next_request().unwrap();
+        let split = format!("{{\"Action\":[{request},{{\"Move\":\"Right\"}}]}}\n");
+        let (first, second) = split.split_at(10);
+        slow.write_all(first.as_bytes()).unwrap();
+        std::thread::sleep(wait);
+        slow.write_all(second.as_bytes()).unwrap();
+
+        while let Some(request) = next_request() {
+            let action = format!("{{\"Action\":[{request},\"DoNothing\"]}}\n");
+            if slow.write_all(action.as_bytes()).is_err() {
+                break;
+            }
+        }
+        fast.join().unwrap();
+    });
+
+    let mut server = Server::new(get_deterministic_config());
+    server.players = 2;
+    server.max_turns = 10;
+    server.timeout = Some(std::time::Duration::from_millis(100));
+    let dungeon = server.run(&listener).unwrap();
+    clients.join().unwrap();
+
+    assert_eq!(dungeon.get_turn(), 10);
+    assert!(dungeon.is_in_game("A"));
+    assert!(dungeon.is_in_game("B"));
+    assert_eq!(dungeon.get_players().count(), 2);
+    let slow = dungeon.get_player("A").unwrap();
+    assert_eq!(slow.direction, Direction::None);
+    assert_eq!(slow.get_statistics().turns, 10);
+}
+
+#[test]
+fn test_game_save_load() {
+    let mut game = rogue_lib::game::Dungeon::new_with(get_deterministic_config());
+    let actions = get_scripted_actions(20);
+    game.add_player("Player".to_string(), Box::new(ReplayBehavior::new(actions)));
+    for _ in 0..10 {
+        game.compute_turn();
+    }
+
+    let file = std::env::temp_dir().join("rogue_lib_test_save.json");
+    let file = file.to_str().unwrap();
+    game.save(file, SaveFormat::Json).unwrap();
+    let loaded = rogue_lib::game::Dungeon::load(file).unwrap();
+    assert_eq!(loaded.checksum(), game.checksum());
+    let header = save::read_header(std::fs::File::open(file).unwrap()).unwrap();
+    assert_eq!(header.format, SAVE_VERSION);
+    assert_eq!(header.seed, 42);
+
+    // salvataggio senza intestazione e con le entità che hanno solo l'attacco
+    let mut legacy = serde_json::to_value(&game).unwrap();
+    for entity in legacy["floors"][0]["players"].as_array_mut().unwrap() {
+        let stats = entity.as_object_mut().unwrap().remove("stats").unwrap();
+        entity["attack"] = stats["attack"].clone();
+    }
+    legacy["config"].as_object_mut().unwrap().remove("items");
+    legacy["config"]["player_stats"]
+        .as_object_mut()
+        .unwrap()
+        .remove("growth");
+    let bytes = serde_json::to_vec(&legacy).unwrap();
+    let header = save::read_header(bytes.as_slice()).unwrap();
+    assert_eq!(header.format, 0);
+    assert_eq!(header.seed, 42);
+    let loaded = save::read(bytes.as_slice()).unwrap();
+    assert_eq!(loaded.checksum(), game.checksum());
+    assert_eq!(loaded.get_players().next().unwrap().get_stats().defense, 0);
+
+    let mut bytes = vec![];
+    save::write(&game, SaveFormat::Json, &mut bytes).unwrap();
+    let mut saved: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    saved["header"]["format"] = (SAVE_VERSION + 1).into();
+    let result = save::read(serde_json::to_vec(&saved).unwrap().as_slice());
+    assert!(matches!(result, Err(SaveError::Version { .. })));
+
+    saved["header"]["format"] = SAVE_VERSION.into();
+    saved["dungeon"]["floors"][0]["players"][0]["behavior"]["type"] = "Unknown".into();
+    let result = save::read(serde_json::to_vec(&saved).unwrap().as_slice());
+    assert!(matches!(result, Err(SaveError::UnknownType(_))));
+
+    let result = save::read("not a save".as_bytes());
+    assert!(matches!(result, Err(SaveError::Parse(_))));
+    let result = rogue_lib::game::Dungeon::load("missing_file.json");
+    assert!(matches!(result, Err(SaveError::Io(_))));
+}
+
+#[test]
+fn test_game_save_console() {
+    let mut game = rogue_lib::game::Dungeon::new_with(get_deterministic_config());
+    game.add_player("Player".to_string(), Box::new(ConsoleInput::new()));
+    game.add_player("Other".to_string(), Box::new(Immovable));
+    assert_eq!(game.get_player_level("Player"), Some(0));
+    assert_eq!(game.get_player_level("Missing"), None);
+
+    let file = std::env::temp_dir().join("rogue_lib_test_save_console.json");
+    let file = file.to_str().unwrap();
+    game.save(file, SaveFormat::Json).unwrap();
+    let loaded = rogue_lib::game::Dungeon::load(file).unwrap();
+    assert_eq!(loaded.checksum(), game.checksum());
+    assert_eq!(loaded.get_player_level("Player"), Some(0));
+
+    let value = serde_json::to_value(&loaded).unwrap();
+    let players = value["floors"][0]["players"].as_array().unwrap();
+    let behaviors = players
+        .iter()
+        .map(|player| player["behavior"]["type"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(behaviors, vec!["ConsoleInput", "Immovable"]);
+}
+
+#[test]
+fn test_game_save_binary() {
+    let mut game = rogue_lib::game::Dungeon::new_with(get_deterministic_config());
+    let actions = get_scripted_actions(20);
+    game.add_player("Player".to_string(), Box::new(ReplayBehavior::new(actions)));
+    for _ in 0..10 {
+        game.compute_turn();
+    }
+
+    let mut json = vec![];
+    save::write(&game, SaveFormat::Json, &mut json).unwrap();
+    let mut binary = vec![];
+    save::write(&game, SaveFormat::Binary, &mut binary).unwrap();
+    assert_eq!(save::detect_format(&json), SaveFormat::Json);
+    assert_eq!(save::detect_format(&binary), SaveFormat::Binary);
+    assert!(binary.len() * 4 < json.len());
+
+    let header = save::read_header(binary.as_slice()).unwrap();
+    assert_eq!(header.format, SAVE_VERSION);
+    assert_eq!(header.seed, 42);
+
+    let from_json = save::read(json.as_slice()).unwrap();
+    let from_binary = save::read(binary.as_slice()).unwrap();
+    assert_eq!(from_json.checksum(), game.checksum());
+    assert_eq!(from_binary.checksum(), game.checksum());
+    let player = from_binary.get_players().next().unwrap();
+    let expected = from_json.get_players().next().unwrap();
+    assert_eq!(player.get_statistics(), expected.get_statistics());
+    assert_eq!(player.get_stats(), expected.get_stats());
+
+    let file = std::env::temp_dir().join("rogue_lib_test_save.bin");
+    let file = file.to_str().unwrap();
+    game.save(file, SaveFormat::Binary).unwrap();
+    let mut loaded = rogue_lib::game::Dungeon::load(file).unwrap();
+    let mut game = from_json;
+    for _ in 0..10 {
+        game.compute_turn();
+        loaded.compute_turn();
+    }
+    assert_eq!(loaded.checksum(), game.checksum());
+
+    let body = &binary[save::BINARY_MAGIC.len()..];
+    let saved: serde_json::Value = rmp_serde::from_slice(body).unwrap();
+    let corrupt = |change: &dyn Fn(&mut serde_json::Value)| {
+        let mut saved = saved.clone();
+        change(&mut saved["dungeon"]["floors"][0]["grid"][0]);
+        let mut bytes = save::BINARY_MAGIC.to_vec();
+        bytes.extend(rmp_serde::to_vec_named(&saved).unwrap());
+        save::read(bytes.as_slice())
+    };
+    assert!(corrupt(&|_| ()).is_ok());
+    assert!(matches!(
+        corrupt(&|row| row[0] = "run".into()),
+        Err(SaveError::Binary(_))
+    ));
+    assert!(matches!(
+        corrupt(&|row| row[0][0] = u64::MAX.into()),
+        Err(SaveError::Binary(_))
+    ));
+    assert!(matches!(
+        corrupt(&|row| row[0][0] = 0.into()),
+        Err(SaveError::Binary(_))
+    ));
+    assert!(matches!(
+        corrupt(&|row| {
+            let run = row[0].clone();
+            row.as_array_mut().unwrap().push(run);
+        }),
+        Err(SaveError::Binary(_))
+    ));
+
+    binary.truncate(binary.len() / 2);
+    let result = save::read(binary.as_slice());
+    assert!(matches!(result, Err(SaveError::Binary(_))));
+}
+
+#[test]
+fn test_game_replay() {
+    let mut game = rogue_lib::game::Dungeon::new_with(get_deterministic_config());
+    game.start_recording();
+    let behavior = ReplayBehavior::new(get_scripted_actions(50));
+    game.add_player("Player".to_string(), Box::new(behavior));
+    for _ in 0..60 {
+        game.compute_turn();
+    }
+    assert!(!game.has_players());
+
+    let replay = game.stop_recording().unwrap();
+    assert_eq!(replay.len(), 60);
+    assert!(game.stop_recording().is_none());
+
+    let file = std::env::temp_dir().join("rogue_lib_test_replay.json");
+    let file = file.to_str().unwrap();
+    replay.save(file).unwrap();
+    let replay = Replay::load(file).unwrap();
+    let _ = std::fs::remove_file(file);
+
+    let played = replay.play().unwrap();
+    assert_eq!(played.get_turn(), game.get_turn());
+    assert_eq!(played.checksum(), game.checksum());
+}
+
+#[test]
+#[should_panic]
+fn test_game_replay_after_players() {
+    let mut game = rogue_lib::game::Dungeon::new_with(get_deterministic_config());
+    game.add_player("Player".to_string(), Box::new(Immovable));
+    game.start_recording();
+}
+
+#[test]
+fn test_game_stable_hasher() {
+    let mut hasher = StableHasher::new();
+    assert_eq!(hasher.finish(), 0xcbf29ce484222325);
+    hasher.write(b"a");
+    assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+
+    let mut hasher = StableHasher::new();
+    hasher.write_u32(1);
+    let mut other = StableHasher::new();
+    other.write(&1u64.to_le_bytes());
+    assert_eq!(hasher.finish(), other.finish());
+}
+
+#[test]
+fn test_game_determinism() {
+    let config = rogue_lib::config::Config {
+        game_seed: 1234,
+        ..Default::default()
+    };
+    let mut game1 = rogue_lib::game::Dungeon::new_with(config.clone());
+    let mut game2 = rogue_lib::game::Dungeon::new_with(config);
+    let actions = get_scripted_actions(500);
+    game1.add_player(
+        "Player".to_string(),
+        Box::new(ReplayBehavior::new(actions.clone())),
+    );
+    game2.add_player("Player".to_string(), Box::new(ReplayBehavior::new(actions)));
+    assert_eq!(game1.checksum(), game2.checksum());
+
+    for _ in 0..500 {
+        game1.compute_turn();
+        game2.compute_turn();
+        assert_eq!(game1.checksum(), game2.checksum());
+    }
+
+    let entities = |game: &rogue_lib::game::Dungeon| {
+        game.get_floor(0)
+            .get_all_entities()
+            .map(|e| (e.get_name().clone(), e.position, e.get_health()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(entities(&game1), entities(&game2));
+}
+
+#[test]
+fn test_game_replay_divergence() {
+    let mut game = rogue_lib::game::Dungeon::new_with(get_deterministic_config());
+    game.start_recording();
+    let behavior = ReplayBehavior::new(get_scripted_actions(20));
+    game.add_player("Player".to_string(), Box::new(behavior));
+    for _ in 0..20 {
+        game.compute_turn();
+    }
+
+    let replay = game.stop_recording().unwrap();
+    let mut json = serde_json::to_value(&replay).unwrap();
+    json["turns"][4]["actions"][0][1] = serde_json::json!("DoNothing");
+    let replay: Replay = serde_json::from_value(json).unwrap();
+
+    let divergence = replay.play().unwrap_err();
+    assert_eq!(divergence.turn, 5);
+    assert_ne!(divergence.expected, divergence.found);
+}
+
+#[test]
+fn test_simulation_report() {
+    let config = rogue_lib::config::Config {
+        game_seed: 7,
+        ..Default::default()
+    };
+    let mut simulation = Simulation::new(config, Box::new(ExitSeeker::new()));
+    simulation.games = 5;
+    simulation.max_turns = 200;
+
+    let report = simulation.run();
+    assert_eq!(report.games.len(), 5);
+    assert!(report.to_string().contains("Games played:  5"));
+    assert_eq!(report, simulation.run());
+    assert!(report.deepest_floor >= 1);
+    assert!(report.average_turns > 0.0);
+    assert!(report.games.iter().all(|game| game.turns <= 200));
+    assert_eq!(
+        report.deaths,
+        report.games.iter().filter(|game| game.died).count()
+    );
+
+    let damage = report
+        .games
+        .iter()
+        .fold(0, |acc, game| acc + game.statistics.get_total_damage());
+    let average = report.average_damage.values().sum::<f32>();
+    assert_eq!((damage as f32 / 5.0).round(), average.round());
+}
+
 #[test]
 fn test_generator_priority() {
     let mut vec = vec![(1_u32, &"a"), (3, &"b"), (2, &"c")].into_iter();
@@ -370,3 +1952,351 @@ fn test_generator_priority() {
         2.0
     );
 }
+
+#[test]
+fn test_generator_alternatives() {
+    let generators: Vec<Box<dyn FloorGenerator>> = vec![
+        Box::new(RoomsAndMazes),
+        Box::new(CellularCaves::default()),
+        Box::new(BspRooms),
+        Box::new(DrunkardWalk::default()),
+    ];
+
+    for generator in generators {
+        let mut config = get_deterministic_config();
+        config.maze_generation.generator = generator.clone();
+        for seed in 0..10 {
+            let mut rng = Pcg32::seed_from_u64(seed);
+            let size = 30;
+            let layout = generator.generate(size, 0, &config.maze_generation, &mut rng);
+            let grid = layout.grid;
+            assert_eq!(grid.len(), size);
+
+            let mut stairs = (0, 0);
+            for (x, col) in grid.iter().enumerate() {
+                assert_eq!(col.len(), size);
+                for (y, cell) in col.iter().enumerate() {
+                    let border = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+                    match cell {
+                        Cell::Entrance => stairs.0 += 1,
+                        Cell::Exit => stairs.1 += 1,
+                        Cell::Wall | Cell::Empty => (),
+                        _ => panic!("{:?} generated unexpected cells", generator),
+                    }
+                    assert!(!border || matches!(cell, Cell::Wall));
+                }
+            }
+            assert_eq!(stairs, (1, 1), "{:?}", generator);
+
+            let floor = Floor::new(0, rng, vec![], grid);
+            let entrance = floor.get_entrance();
+            let exit = floor.get_exit().unwrap();
+            let path = floor.find_path(&entrance, &exit, PathOptions::default());
+            assert!(path.is_some(), "{:?} with seed {}", generator, seed);
+        }
+
+        let game = rogue_lib::game::Dungeon::new_with(config.clone());
+        assert_eq!(game.get_floor(0).get_level(), 0);
+        let json = serde_json::to_string(&config).unwrap();
+        let loaded: rogue_lib::config::Config = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            format!("{:?}", loaded.maze_generation.generator),
+            format!("{:?}", generator)
+        );
+    }
+
+    let config = get_deterministic_config();
+    let generator = DrunkardWalk { coverage: 100 };
+    let mut rng = Pcg32::seed_from_u64(0);
+    let layout = generator.generate(MIN_FLOOR_SIZE, 0, &config.maze_generation, &mut rng);
+    let walls = layout.grid.iter().flatten();
+    assert_eq!(walls.filter(|cell| matches!(cell, Cell::Wall)).count(), 16);
+}
+
+#[test]
+fn test_generator_caves_fallback() {
+    let caves = CellularCaves {
+        fill_percentage: 100,
+        iterations: 4,
+    };
+    let mut config = get_deterministic_config();
+    config.maze_generation.generator = Box::new(caves.clone());
+    let problem = "fill_percentage must be below 100 (found 100)".to_string();
+    assert_eq!(
+        config.validate(),
+        Err(vec![ConfigError::Generator(problem)])
+    );
+    assert!(CellularCaves::default().validate().is_ok());
+
+    let mut rng = Pcg32::seed_from_u64(0);
+    let layout = caves.generate(30, 0, &config.maze_generation, &mut rng);
+    let floor = Floor::new(0, rng, vec![], layout.grid);
+    let entrance = floor.get_entrance();
+    let exit = floor.get_exit().unwrap();
+    let path = floor.find_path(&entrance, &exit, PathOptions::default());
+    assert!(path.is_some());
+}
+
+#[test]
+fn test_generator_stairs_distance() {
+    let mut config = get_deterministic_config();
+    config.maze_generation.min_stairs_distance = 25;
+    let generators: Vec<Box<dyn FloorGenerator>> = vec![
+        Box::new(RoomsAndMazes),
+        Box::new(CellularCaves::default()),
+        Box::new(BspRooms),
+        Box::new(DrunkardWalk::default()),
+    ];
+
+    for generator in generators {
+        config.maze_generation.generator = generator;
+        for seed in 0..10 {
+            let floor = Generator::new(seed, 0, &config).build_floor();
+            let entrance = floor.get_entrance();
+            let exit = floor.get_exit().unwrap();
+            let options = PathOptions::default();
+            let path = floor.find_path(&entrance, &exit, options).unwrap();
+            assert!(path.len() >= 25);
+
+            let other = Generator::new(seed, 0, &config).build_floor();
+            assert_eq!(other.get_entrance(), entrance);
+            assert_eq!(other.get_exit(), Some(exit));
+        }
+    }
+}
+
+#[test]
+fn test_generator_prefab() {
+    let mut prefab = Prefab::new(
+        "vault".to_string(),
+        "......\n.#..#.\n.#E*#.\n.####.\n......",
+    );
+    prefab
+        .legend
+        .insert('E', PrefabTile::Spawn("Basic enemy".to_string()));
+    prefab
+        .legend
+        .insert('*', PrefabTile::Effect(Box::new(InstantDamage(-10))));
+    assert_eq!(prefab.get_width(), 6);
+    assert_eq!(prefab.get_height(), 5);
+
+    let json = serde_json::to_string(&prefab).unwrap();
+    let prefab: Prefab = serde_json::from_str(&json).unwrap();
+    assert_eq!(prefab.legend.len(), 2);
+
+    let mut config = rogue_lib::config::Config {
+        entities_total: 0,
+        effects_total: 0,
+        items_total: 0,
+        ..get_deterministic_config()
+    };
+    config.maze_generation.prefabs.push(ConfigPrefab {
+        floors: 1..2,
+        prefab,
+        chance: 100,
+    });
+
+    let floor = Generator::new(0, 0, &config).build_floor();
+    assert_eq!(floor.get_all_entities().count(), 0);
+
+    for seed in 0..10 {
+        let floor = Generator::new(seed, 1, &config).build_floor();
+        let enemies = floor.get_all_entities().collect::<Vec<_>>();
+        assert_eq!(enemies.len(), 1);
+        assert_eq!(enemies[0].get_name(), "Basic enemy");
+
+        // l'effetto è alla destra del nemico, e sotto di essi c'è il muro della stanza
+        let mut pos = enemies[0].position;
+        Direction::Right.move_from(&mut pos);
+        assert!(matches!(floor.get_cell(&pos), Cell::Special(_)));
+        Direction::Down.move_from(&mut pos);
+        assert!(matches!(floor.get_cell(&pos), Cell::Wall));
+        Direction::Left.move_from(&mut pos);
+        assert!(matches!(floor.get_cell(&pos), Cell::Wall));
+    }
+    assert_eq!(config.validate(), Ok(()));
+
+    let boss = config.bosses[0].boss.name.clone();
+    let legend = &mut config.maze_generation.prefabs[0].prefab.legend;
+    legend.insert('E', PrefabTile::Spawn(boss.clone()));
+    assert_eq!(config.validate(), Ok(()));
+    let floor = Generator::new(0, 1, &config).build_floor();
+    assert_eq!(floor.get_boss().unwrap().get_name(), &boss);
+    assert!(floor.is_exit_locked());
+
+    let legend = &mut config.maze_generation.prefabs[0].prefab.legend;
+    legend.insert('E', PrefabTile::Spawn("Basic enemi".to_string()));
+    let errors = config.validate().unwrap_err();
+    let unknown = ConfigError::UnknownSpawn {
+        prefab: "vault".to_string(),
+        name: "Basic enemi".to_string(),
+    };
+    assert_eq!(errors, vec![unknown]);
+    assert_eq!(
+        errors[0].to_string(),
+        "prefab vault spawns Basic enemi, which is not a configured entity"
+    );
+}
+
+#[test]
+fn test_floor_boss() {
+    let config = rogue_lib::config::Config {
+        entities_total: 0,
+        effects_total: 0,
+        items_total: 0,
+        ..get_deterministic_config()
+    };
+    let boss = &config.bosses[0];
+    assert!(!boss.is_boss_floor(0));
+    assert!(boss.is_boss_floor(boss.every - 1));
+
+    let floor = Generator::new(0, 0, &config).build_floor();
+    assert!(floor.get_boss().is_none());
+    assert!(!floor.is_exit_locked());
+
+    let mut floor = Generator::new(0, boss.every - 1, &config).build_floor();
+    let exit = floor.get_exit().unwrap();
+    let boss_pos = floor.get_boss().unwrap().position;
+    assert_eq!(floor.get_boss().unwrap().get_name(), &boss.boss.name);
+    assert_eq!(floor.get_all_entities().count(), 1 + boss.minions.len());
+    assert!(floor.is_exit_locked());
+    assert_eq!(floor.get_stairs_at(&exit), None);
+
+    let impostor = Entity::new(boss.boss.name.clone(), 10, 0, Box::new(Immovable));
+    floor.add_entity_near(impostor, &boss_pos);
+    let impostor_pos = floor.get_all_entities().last().unwrap().position;
+    assert_eq!(floor.get_boss().unwrap().position, boss_pos);
+
+    let game = rogue_lib::game::Dungeon::new_with(config.clone());
+    let mut bytes = vec![];
+    save::write(&game, SaveFormat::Json, &mut bytes).unwrap();
+    let mut saved: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    saved["header"]["format"] = 1.into();
+    let legacy = &mut saved["dungeon"]["floors"][0];
+    legacy["boss"] = "name".into();
+    let entities = legacy["entities"].as_array_mut().unwrap();
+    entities.push(serde_json::to_value(get_basic_entity()).unwrap());
+    entities.push(serde_json::to_value(get_basic_entity()).unwrap());
+    for entity in entities.iter_mut() {
+        entity.as_object_mut().unwrap().remove("boss");
+    }
+    let loaded = save::read(serde_json::to_vec(&saved).unwrap().as_slice()).unwrap();
+    let bosses = loaded.get_floor(0).get_all_entities().filter(|e| e.boss);
+    assert_eq!(bosses.count(), 1);
+    assert_eq!(loaded.get_floor(0).get_boss().unwrap().get_name(), "name");
+
+    floor.get_entity_at(&boss_pos).unwrap().apply_damage(1000);
+    floor.update_entities();
+    assert!(floor.get_boss().is_none());
+    assert!(!floor.is_exit_locked());
+    assert_eq!(floor.get_stairs_at(&exit), Some(Stairs::Down));
+    assert!(floor.get_entity_at(&impostor_pos).is_some());
+
+    let json = serde_json::to_string(&config).unwrap();
+    let loaded: rogue_lib::config::Config = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.bosses.len(), config.bosses.len());
+}
+
+#[test]
+fn test_tui_frame_diff() {
+    let mut frame = Frame::new(10, 3);
+    frame.put_box(0, 0, 10, 3, " T ", "");
+    frame.put_str(1, 1, 3, "hello", "");
+    assert_eq!(frame.get_line(0), "╔══ T ═══╗");
+    assert_eq!(frame.get_line(1), "║hel     ║");
+    assert_eq!(frame.get_line(2), "╚════════╝");
+    frame.put(20, 20, 'x', "");
+    assert_eq!(frame.get(20, 20), None);
+
+    let full = frame.diff(None);
+    assert!(full.starts_with("\x1b[2J"));
+    assert!(full.contains("hel"));
+
+    let mut next = frame.clone();
+    assert_eq!(next.diff(Some(&frame)), "");
+    next.put(5, 1, 'o', "");
+    next.put(6, 1, 'k', "");
+    let diff = next.diff(Some(&frame));
+    assert!(diff.starts_with("\x1b[2;6H"));
+    assert!(diff.contains("ok"));
+    assert!(!diff.contains("hel"));
+
+    let resized = Frame::new(12, 3);
+    assert!(resized.diff(Some(&frame)).starts_with("\x1b[2J"));
+}
+
+#[test]
+fn test_tui_view() {
+    let rng = Pcg32::seed_from_u64(0);
+    let mut floor = Floor::new(2, rng, vec![], vec![vec![Cell::Empty; 20]; 20]);
+    *floor.get_cell_mut(&Position(10, 10)) = Cell::Entrance;
+    *floor.get_cell_mut(&Position(9, 10)) = Cell::Special(Box::new(InstantDamage(5)));
+    let mut player = get_basic_entity();
+    player.add_effect(Box::new(TurnBasedDamage::new(3, 1)));
+    let _ = player.add_item(Box::new(Weapon::new("Sword".to_string(), 5)));
+    floor.add_player(player);
+
+    let entity = floor.get_players().next().unwrap();
+    let view = FloorView::new(&floor, entity);
+    let rows = view
+        .get_viewport(6, 4)
+        .map(|row| row.map(|cell| cell.position).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(rows.len(), 4);
+    assert!(rows.iter().all(|row| row.len() == 6));
+    assert_eq!(rows[0][0], Position(7, 11));
+    assert_eq!(rows[3][5], Position(12, 8));
+
+    let frame = tui::draw_view(&view, 80, 23);
+    let lines = (0..frame.get_height())
+        .map(|y| frame.get_line(y))
+        .collect::<Vec<_>>();
+    assert!(lines[0].contains(" Floor lv. 2 "));
+    assert!(lines[0].contains(" name "));
+    let panel = lines
+        .iter()
+        .map(|line| line.chars().skip(80 - PANEL_WIDTH).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert!(panel.contains("100/100"));
+    assert!(panel.contains("║ Inventory:"));
+    assert!(panel.contains("║  [1] Sword"));
+    assert!(panel.contains("║  Damage over time"));
+    assert!(panel.contains("║  - Instant damage"));
+    assert!(panel.contains('@'));
+
+    let map = lines
+        .iter()
+        .map(|line| line.chars().take(80 - PANEL_WIDTH).collect::<String>())
+        .collect::<String>();
+    assert!(map.contains(entity.direction.as_char()));
+    assert!(map.contains('-'));
+
+    let look = LookMap::new(&view, 80, 23);
+    assert_eq!(
+        look.describe(&Position(9, 10)),
+        ["Instant damage: Deals 5 damage once"]
+    );
+    assert!(look.describe(&Position(10, 10))[0].starts_with("name (you): Lv.1 HP 100/100"));
+    assert_eq!(look.describe(&Position(10, 10))[1], "Entrance of the floor");
+    assert_eq!(
+        look.step(Position(9, 10), Direction::Right),
+        Position(10, 10)
+    );
+    assert_eq!(look.step(Position(0, 10), Direction::Left), Position(0, 10));
+    assert_eq!(look.describe(&Position(40, 40)), [] as [String; 0]);
+
+    let mut looking = frame.clone();
+    tui::draw_look(&mut looking, &look, &Position(9, 10));
+    let (x, y) = look.get_frame_position(&Position(9, 10)).unwrap();
+    assert_eq!(looking.get(x, y).unwrap().ch, '-');
+    assert_ne!(looking.get(x, y), frame.get(x, y));
+    assert!(looking
+        .get_line(23 - 5)
+        .contains("Instant damage: Deals 5 damage once"));
+
+    let mut popup = frame.clone();
+    tui::draw_popup(&mut popup, " Help ", &["line".to_string()], "");
+    assert_ne!(popup, frame);
+    assert!(popup.get_line(11).contains("║ line   ║"));
+}